@@ -0,0 +1,48 @@
+use nbt_rust::{de::from_bytes_flavored, error::Result, flavor::BedrockNetwork, ser::Serializer};
+
+fn main() -> Result<()> {
+    // NBT as it appears in a Bedrock packet
+    let payload = payload();
+
+    // Deserializes the payload
+    let (name, tag) = from_bytes_flavored(&payload, BedrockNetwork)?;
+
+    println!("Name: {name:?}");
+    println!("Value: {tag}");
+
+    // Writes the tag back, which reproduces the payload byte for byte
+    let mut ser = Serializer::new(Vec::new()).with_flavor(BedrockNetwork);
+    ser.serialize_tag(&name, &tag)?;
+    let written = ser.into_inner()?;
+
+    assert_eq!(written, payload);
+    println!("Round trip: {} bytes, identical", written.len());
+
+    Ok(())
+}
+
+// A nameless compound with a string, an int, a long and an int array.
+// Ints and lengths are zig-zag VarInts, so -1 is 0x01 and 300 is 0xd8 0x04.
+fn payload() -> Vec<u8> {
+    let mut payload = vec![0x0a, 0x00];
+
+    payload.extend([0x08, 0x04]);
+    payload.extend(b"name");
+    payload.push(0x05);
+    payload.extend(b"Steve");
+
+    payload.extend([0x03, 0x02]);
+    payload.extend(b"xp");
+    payload.push(0x01);
+
+    payload.extend([0x04, 0x04]);
+    payload.extend(b"time");
+    payload.extend([0xd8, 0x04]);
+
+    payload.extend([0x0b, 0x03]);
+    payload.extend(b"pos");
+    payload.extend([0x06, 0x02, 0x03, 0x80, 0x01]);
+
+    payload.push(0x00);
+    payload
+}
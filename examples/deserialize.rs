@@ -38,5 +38,5 @@ fn data() -> Result<Vec<u8>> {
 
     // Prints the value of the owned writer.
     // into_inner is called twice because the Vec is wrapped in a Cursor.
-    Ok(ser.into_inner().into_inner())
+    Ok(ser.into_inner()?.into_inner())
 }
@@ -1,8 +1,8 @@
 use nbt_rust::{de::from_bytes, error::Result};
 
 fn main() -> Result<()> {
-    // Well I'm a bit cheating here
-    // I ungzipped the file beforehand
+    // The file is gzipped just like a real level.dat,
+    // from_bytes detects that and decompresses it on the fly
     let file = include_bytes!("file.nbt");
 
     let (name, value) = from_bytes(file)?;
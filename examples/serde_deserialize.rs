@@ -0,0 +1,47 @@
+use nbt_rust::{error::Result, from_bytes};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct LevelDat {
+    #[serde(rename = "Data")]
+    data: Data,
+}
+
+// Only the fields we care about, everything else in the file is skipped
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Data {
+    level_name: String,
+    spawn_x: i32,
+    spawn_y: i32,
+    spawn_z: i32,
+    version: Version,
+    #[serde(rename = "raining")]
+    raining: bool,
+    // Would stay None if the file had no such tag
+    wandering_trader_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Version {
+    name: String,
+    id: i32,
+    snapshot: bool,
+}
+
+fn main() -> Result<()> {
+    // The same gzipped level.dat as in examples/from_file.rs
+    let file = include_bytes!("file.nbt");
+
+    let LevelDat { data } = from_bytes(file)?;
+    println!("Level: {}", data.level_name);
+    println!("Spawn: {} {} {}", data.spawn_x, data.spawn_y, data.spawn_z);
+    println!(
+        "Version: {} ({}, snapshot: {})",
+        data.version.name, data.version.id, data.version.snapshot
+    );
+    println!("Raining: {}", data.raining);
+    println!("Wandering trader: {:?}", data.wandering_trader_id);
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use nbt_rust::{de::from_bytes, error::Result, to_bytes};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Item {
+    id: String,
+    #[serde(rename = "Count")]
+    count: i8,
+    // Omitted from the compound when None
+    tag: Option<HashMap<String, i32>>,
+}
+
+#[derive(Serialize)]
+struct Player {
+    name: String,
+    health: f32,
+    on_ground: bool,
+    pos: (f64, f64, f64),
+    inventory: Vec<Item>,
+}
+
+fn main() -> Result<()> {
+    let player = Player {
+        name: "Steve".to_string(),
+        health: 20.0,
+        on_ground: true,
+        pos: (0.5, 64.0, -12.5),
+        inventory: vec![
+            Item {
+                id: "minecraft:stone".to_string(),
+                count: 64,
+                tag: None,
+            },
+            Item {
+                id: "minecraft:diamond_sword".to_string(),
+                count: 1,
+                tag: Some(HashMap::from([("Damage".to_string(), 12)])),
+            },
+        ],
+    };
+
+    // Serializes the struct as a compound tag named "player"
+    let bytes = to_bytes("player", &player)?;
+    println!("{bytes:?}");
+
+    // Reads the bytes back as an untyped tag to show the resulting structure
+    let (name, value) = from_bytes(&bytes)?;
+    println!("{name}: {value}");
+
+    Ok(())
+}
@@ -27,6 +27,6 @@ fn main() -> Result<()> {
 
     // Prints the value of the owned writer.
     // into_inner is called twice because the Vec is wrapped in a Cursor.
-    println!("{:?}", ser.into_inner().into_inner());
+    println!("{:?}", ser.into_inner()?.into_inner());
     Ok(())
 }
@@ -0,0 +1,197 @@
+//! `#[derive(Nbt)]`
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    ext::IdentExt, parse_quote, Data, DeriveInput, Error, Expr, Field, Fields, GenericParam,
+    Generics, Ident, LitStr, Result,
+};
+
+/// The options of a field set with `#[nbt(...)]`
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    /// The array tag the field is stored as instead of a List
+    array: Option<Ident>,
+    default: bool,
+    flatten: bool,
+    skip_if: Option<Expr>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &Field) -> Result<Self> {
+        let mut attrs = FieldAttrs::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("nbt")) {
+            attr.parse_nested_meta(|meta| {
+                let array = match &meta.path {
+                    p if p.is_ident("byte_array") => Some("ByteArray"),
+                    p if p.is_ident("int_array") => Some("IntArray"),
+                    p if p.is_ident("long_array") => Some("LongArray"),
+                    _ => None,
+                };
+                if let Some(array) = array {
+                    if attrs.array.is_some() {
+                        return Err(meta.error("Only one array type can be given"));
+                    }
+                    attrs.array = Some(Ident::new(array, Span::call_site()));
+                } else if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    attrs.default = true;
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                } else if meta.path.is_ident("skip_if") {
+                    attrs.skip_if = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error(
+                        "Unknown attribute, expected rename, byte_array, int_array, long_array, \
+                         default, flatten or skip_if",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        if attrs.flatten && (attrs.rename.is_some() || attrs.array.is_some() || attrs.default) {
+            return Err(Error::new_spanned(
+                field,
+                "A flattened field cannot be renamed, stored as an array or defaulted",
+            ));
+        }
+        Ok(attrs)
+    }
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(unsupported(&input)),
+        },
+        _ => return Err(unsupported(&input)),
+    };
+
+    let mut to_fields = Vec::new();
+    let mut from_fields = Vec::new();
+    let mut flattened = 0;
+    for field in fields {
+        let attrs = FieldAttrs::parse(field)?;
+        let ident = field.ident.as_ref().expect("named fields have names");
+        let ty = &field.ty;
+        let key = attrs
+            .rename
+            .clone()
+            .unwrap_or_else(|| ident.unraw().to_string());
+
+        if attrs.flatten {
+            let to = quote! {
+                ::nbt_rust::convert::flatten_into(
+                    &mut compound,
+                    ::nbt_rust::convert::ToNbt::to_nbt(&self.#ident),
+                );
+            };
+            to_fields.push(skip_if(&attrs, ident, to));
+            from_fields.push(quote! {
+                #ident: ::nbt_rust::convert::FromNbt::from_nbt(tag)?
+            });
+            flattened += 1;
+            continue;
+        }
+
+        let value = match &attrs.array {
+            Some(array) => quote!(::nbt_rust::convert::#array(&self.#ident)),
+            None => quote!(&self.#ident),
+        };
+        let to = quote! {
+            if let ::core::option::Option::Some(tag) =
+                ::nbt_rust::convert::ToNbt::to_nbt_field(&#value)
+            {
+                compound.insert(::std::string::String::from(#key), tag);
+            }
+        };
+        to_fields.push(skip_if(&attrs, ident, to));
+
+        let read = match attrs.default {
+            true => quote!(::nbt_rust::convert::field_or_default),
+            false => quote!(::nbt_rust::convert::field),
+        };
+        from_fields.push(match &attrs.array {
+            Some(array) => quote! {
+                #ident: #read::<::nbt_rust::convert::#array<#ty>>(compound, #key)?.0
+            },
+            None => quote!(#ident: #read::<#ty>(compound, #key)?),
+        });
+    }
+
+    // Avoids unused variable warnings in the generated code for structs without such fields
+    let compound_mut = match to_fields.is_empty() {
+        true => quote!(compound),
+        false => quote!(mut compound),
+    };
+    let read_compound = match from_fields.len() == flattened {
+        true => quote!(::nbt_rust::convert::expect_compound(tag)?;),
+        false => quote!(let compound = ::nbt_rust::convert::expect_compound(tag)?;),
+    };
+
+    let name = &input.ident;
+    let to_generics = with_bound(&input.generics, quote!(::nbt_rust::convert::ToNbt));
+    let (to_impl, to_ty, to_where) = to_generics.split_for_impl();
+    let from_generics = with_bound(&input.generics, quote!(::nbt_rust::convert::FromNbt));
+    let (from_impl, from_ty, from_where) = from_generics.split_for_impl();
+
+    Ok(quote! {
+        impl #to_impl ::nbt_rust::convert::ToNbt for #name #to_ty #to_where {
+            fn to_nbt(&self) -> ::nbt_rust::NbtTag {
+                let #compound_mut = ::nbt_rust::nbt_tag::NbtCompound::new();
+                #(#to_fields)*
+                ::nbt_rust::NbtTag::Compound(compound)
+            }
+        }
+
+        impl #from_impl ::nbt_rust::convert::FromNbt for #name #from_ty #from_where {
+            fn from_nbt(tag: &::nbt_rust::NbtTag) -> ::nbt_rust::error::Result<Self> {
+                #read_compound
+                ::core::result::Result::Ok(Self {
+                    #(#from_fields,)*
+                })
+            }
+        }
+    })
+}
+
+/// Only writes the field if the `skip_if` predicate, if any, is false
+fn skip_if(attrs: &FieldAttrs, ident: &Ident, write: TokenStream) -> TokenStream {
+    match &attrs.skip_if {
+        Some(predicate) => quote! {
+            if !(#predicate)(&self.#ident) {
+                #write
+            }
+        },
+        None => write,
+    }
+}
+
+/// Requires every type parameter to implement the derived trait
+fn with_bound(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            GenericParam::Type(t) => Some(t.ident.clone()),
+            _ => None,
+        })
+        .collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+fn unsupported(input: &DeriveInput) -> Error {
+    Error::new_spanned(
+        &input.ident,
+        "#[derive(Nbt)] only supports structs with named fields",
+    )
+}
@@ -0,0 +1,21 @@
+//! Procedural macros for `nbt_rust`, re-exported by it with the `macros` feature
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod derive;
+mod nbt;
+
+/// Builds an `NbtTag` from SNBT-like syntax; see the documentation of `nbt_rust::nbt`
+#[proc_macro]
+pub fn nbt(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as nbt::Value).expand().into()
+}
+
+/// Derives `ToNbt` and `FromNbt`; see the documentation of `nbt_rust::Nbt`
+#[proc_macro_derive(Nbt, attributes(nbt))]
+pub fn derive_nbt(input: TokenStream) -> TokenStream {
+    derive::expand(parse_macro_input!(input as DeriveInput))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
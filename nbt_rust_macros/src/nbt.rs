@@ -0,0 +1,360 @@
+//! The `nbt!` macro
+
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{
+    braced, bracketed,
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    Error, Expr, Ident, Lit, LitFloat, LitInt, LitStr, Result, Token,
+};
+
+/// The statically known type of a tag, used to reject mixed lists
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    ByteArray,
+    String,
+    List,
+    Compound,
+    IntArray,
+    LongArray,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Byte => "Byte",
+            Kind::Short => "Short",
+            Kind::Int => "Int",
+            Kind::Long => "Long",
+            Kind::Float => "Float",
+            Kind::Double => "Double",
+            Kind::ByteArray => "ByteArray",
+            Kind::String => "String",
+            Kind::List => "List",
+            Kind::Compound => "Compound",
+            Kind::IntArray => "IntArray",
+            Kind::LongArray => "LongArray",
+        }
+    }
+}
+
+pub(crate) enum Value {
+    /// A literal, already checked and expanded
+    Literal(Kind, TokenStream),
+    Compound(Vec<(String, Value)>),
+    List(Vec<Value>),
+    Array(Kind, Vec<TokenStream>),
+    /// An interpolated Rust expression
+    Expr(Box<Expr>),
+}
+
+impl Value {
+    fn kind(&self) -> Option<Kind> {
+        match self {
+            Value::Literal(kind, _) | Value::Array(kind, _) => Some(*kind),
+            Value::Compound(_) => Some(Kind::Compound),
+            Value::List(_) => Some(Kind::List),
+            Value::Expr(_) => None,
+        }
+    }
+
+    pub(crate) fn expand(&self) -> TokenStream {
+        match self {
+            Value::Literal(_, tokens) => tokens.clone(),
+            Value::Compound(entries) => {
+                let len = entries.len();
+                let inserts = entries.iter().map(|(key, value)| {
+                    let value = value.expand();
+                    quote!(compound.insert(::std::string::String::from(#key), #value);)
+                });
+                quote!({
+                    let mut compound = ::nbt_rust::nbt_tag::NbtCompound::with_capacity(#len);
+                    #(#inserts)*
+                    ::nbt_rust::NbtTag::Compound(compound)
+                })
+            }
+            Value::List(elems) => {
+                let elems = elems.iter().map(Value::expand);
+                quote!(::nbt_rust::NbtTag::List(
+                    ::nbt_rust::nbt_tag::NbtList::from_macro_elements(::std::vec![#(#elems),*])
+                ))
+            }
+            Value::Array(kind, elems) => {
+                let variant = Ident::new(kind.name(), Span::call_site());
+                quote!(::nbt_rust::NbtTag::#variant(::std::vec![#(#elems),*]))
+            }
+            Value::Expr(expr) => quote_spanned!(span_of(expr)=>
+                ::nbt_rust::NbtTag::from(#expr)
+            ),
+        }
+    }
+}
+
+fn span_of(expr: &Expr) -> Span {
+    syn::spanned::Spanned::span(expr)
+}
+
+impl Parse for Value {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            return parse_compound(&content);
+        }
+        if input.peek(syn::token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            return parse_list(&content);
+        }
+        if let Some((negative, lit)) = peek_literal(input)? {
+            return parse_literal(negative, lit);
+        }
+        Ok(Value::Expr(Box::new(input.parse()?)))
+    }
+}
+
+fn parse_compound(input: ParseStream) -> Result<Value> {
+    let mut entries: Vec<(String, Value)> = Vec::new();
+    while !input.is_empty() {
+        let (key, span) = if input.peek(LitStr) {
+            let key: LitStr = input.parse()?;
+            (key.value(), key.span())
+        } else {
+            let key = Ident::parse_any(input)?;
+            (key.unraw().to_string(), key.span())
+        };
+        if entries.iter().any(|(k, _)| *k == key) {
+            return Err(Error::new(span, format!("Duplicate key {key:?}")));
+        }
+        input.parse::<Token![:]>()?;
+        entries.push((key, input.parse()?));
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+    }
+    Ok(Value::Compound(entries))
+}
+
+fn parse_list(input: ParseStream) -> Result<Value> {
+    if input.peek(Ident) && input.peek2(Token![;]) {
+        let prefix: Ident = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let kind = match prefix.to_string().as_str() {
+            "B" => Kind::ByteArray,
+            "I" => Kind::IntArray,
+            "L" => Kind::LongArray,
+            _ => {
+                return Err(Error::new(
+                    prefix.span(),
+                    "Invalid array type, expected B, I or L",
+                ))
+            }
+        };
+        return parse_array(input, kind);
+    }
+
+    let mut elems: Vec<Value> = Vec::new();
+    let mut list_kind: Option<Kind> = None;
+    while !input.is_empty() {
+        let span = input.span();
+        let elem: Value = input.parse()?;
+        match (list_kind, elem.kind()) {
+            (Some(expected), Some(found)) if expected != found => {
+                return Err(Error::new(
+                    span,
+                    format!(
+                        "Mixed tag types in a list: expected {}, found {}",
+                        expected.name(),
+                        found.name()
+                    ),
+                ));
+            }
+            (None, found) => list_kind = found,
+            _ => {}
+        }
+        elems.push(elem);
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+    }
+    Ok(Value::List(elems))
+}
+
+fn parse_array(input: ParseStream, kind: Kind) -> Result<Value> {
+    let elem_kind = match kind {
+        Kind::ByteArray => Kind::Byte,
+        Kind::IntArray => Kind::Int,
+        _ => Kind::Long,
+    };
+
+    let mut elems = Vec::new();
+    while !input.is_empty() {
+        let span = input.span();
+        let elem = match peek_literal(input)? {
+            Some((negative, Lit::Int(lit))) => {
+                // Unsuffixed literals take the element type of the array
+                let suffix_kind = match lit.suffix() {
+                    "" => elem_kind,
+                    suffix => {
+                        int_suffix_kind(suffix).ok_or_else(|| unknown_suffix(span, suffix))?
+                    }
+                };
+                if suffix_kind != elem_kind {
+                    return Err(array_element_error(span, kind, elem_kind));
+                }
+                let digits = sign(negative, lit.base10_digits());
+                let value = match kind {
+                    Kind::ByteArray => digits
+                        .parse::<i8>()
+                        .ok()
+                        .map(|b| Literal::u8_suffixed(b as u8)),
+                    _ => typed_literal(elem_kind, &digits),
+                };
+                let value = value.ok_or_else(|| out_of_range(span, elem_kind))?;
+                quote!(#value)
+            }
+            Some(_) => return Err(array_element_error(span, kind, elem_kind)),
+            None => {
+                let expr: Expr = input.parse()?;
+                match kind {
+                    Kind::ByteArray => quote_spanned!(span_of(&expr)=>
+                        ::core::convert::Into::<i8>::into(#expr) as u8
+                    ),
+                    Kind::IntArray => quote_spanned!(span_of(&expr)=>
+                        ::core::convert::Into::<i32>::into(#expr)
+                    ),
+                    _ => quote_spanned!(span_of(&expr)=>
+                        ::core::convert::Into::<i64>::into(#expr)
+                    ),
+                }
+            }
+        };
+        elems.push(elem);
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+    }
+    Ok(Value::Array(kind, elems))
+}
+
+fn array_element_error(span: Span, kind: Kind, elem_kind: Kind) -> Error {
+    Error::new(
+        span,
+        format!("{} elements must be {}s", kind.name(), elem_kind.name()),
+    )
+}
+
+/// Consumes a literal, with an optional leading minus sign, if one comes next
+fn peek_literal(input: ParseStream) -> Result<Option<(bool, Lit)>> {
+    if input.peek(Token![-]) && (input.peek2(LitInt) || input.peek2(LitFloat)) {
+        input.parse::<Token![-]>()?;
+        return Ok(Some((true, input.parse()?)));
+    }
+    if input.peek(Lit) {
+        return Ok(Some((false, input.parse()?)));
+    }
+    Ok(None)
+}
+
+fn parse_literal(negative: bool, lit: Lit) -> Result<Value> {
+    let (kind, digits, span) = match lit {
+        Lit::Str(s) => {
+            let tokens = quote!(::nbt_rust::NbtTag::String(::std::string::String::from(#s)));
+            return Ok(Value::Literal(Kind::String, tokens));
+        }
+        Lit::Bool(b) => {
+            let value = Literal::i8_suffixed(b.value as i8);
+            return Ok(Value::Literal(
+                Kind::Byte,
+                quote!(::nbt_rust::NbtTag::Byte(#value)),
+            ));
+        }
+        Lit::Int(lit) => {
+            let kind = int_suffix_kind(lit.suffix())
+                .ok_or_else(|| unknown_suffix(lit.span(), lit.suffix()))?;
+            (kind, sign(negative, lit.base10_digits()), lit.span())
+        }
+        Lit::Float(lit) => {
+            let kind = match lit.suffix() {
+                "" | "d" | "D" | "f64" => Kind::Double,
+                "f" | "F" | "f32" => Kind::Float,
+                suffix => return Err(unknown_suffix(lit.span(), suffix)),
+            };
+            (kind, sign(negative, lit.base10_digits()), lit.span())
+        }
+        lit => {
+            return Err(Error::new(
+                lit.span(),
+                "Unsupported literal, expected a number, a string or a bool",
+            ))
+        }
+    };
+
+    let value = typed_literal(kind, &digits).ok_or_else(|| out_of_range(span, kind))?;
+    let variant = Ident::new(kind.name(), Span::call_site());
+    Ok(Value::Literal(
+        kind,
+        quote!(::nbt_rust::NbtTag::#variant(#value)),
+    ))
+}
+
+/// The tag type an integer literal with the given SNBT or Rust suffix stands for
+fn int_suffix_kind(suffix: &str) -> Option<Kind> {
+    match suffix {
+        "" | "i32" => Some(Kind::Int),
+        "b" | "B" | "i8" => Some(Kind::Byte),
+        "s" | "S" | "i16" => Some(Kind::Short),
+        "l" | "L" | "i64" => Some(Kind::Long),
+        "f" | "F" | "f32" => Some(Kind::Float),
+        "d" | "D" | "f64" => Some(Kind::Double),
+        _ => None,
+    }
+}
+
+/// A Rust literal of the number type backing the tag type, if the value fits
+fn typed_literal(kind: Kind, digits: &str) -> Option<Literal> {
+    match kind {
+        Kind::Byte => digits.parse().ok().map(Literal::i8_suffixed),
+        Kind::Short => digits.parse().ok().map(Literal::i16_suffixed),
+        Kind::Int => digits.parse().ok().map(Literal::i32_suffixed),
+        Kind::Long => digits.parse().ok().map(Literal::i64_suffixed),
+        Kind::Float => digits
+            .parse::<f32>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(Literal::f32_suffixed),
+        _ => digits
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(Literal::f64_suffixed),
+    }
+}
+
+fn sign(negative: bool, digits: &str) -> String {
+    match negative {
+        true => format!("-{digits}"),
+        false => digits.to_string(),
+    }
+}
+
+fn unknown_suffix(span: Span, suffix: &str) -> Error {
+    Error::new(
+        span,
+        format!("Unknown suffix `{suffix}`, expected b, s, l, f, d or a Rust number type"),
+    )
+}
+
+fn out_of_range(span: Span, kind: Kind) -> Error {
+    Error::new(span, format!("Literal out of range for {}", kind.name()))
+}
@@ -0,0 +1,209 @@
+use std::io::{Cursor, Read, Write};
+
+use crate::{
+    de::Decoder,
+    error::{Error, Result},
+    flavor::Bedrock,
+    ser::Serializer,
+    NbtTag,
+};
+
+/// A Bedrock Edition `level.dat`
+///
+/// The little-endian NBT of the file is preceded by an 8-byte header holding the
+/// storage version of the world and the length of the NBT payload. The header is
+/// validated when reading, and the length is recomputed when writing.
+///
+/// ```no_run
+/// use nbt_rust::bedrock::BedrockLevelDat;
+///
+/// # fn main() -> nbt_rust::error::Result<()> {
+/// let file = std::fs::read("level.dat")?;
+/// let mut level = BedrockLevelDat::from_bytes(&file)?;
+/// println!("Storage version: {}", level.storage_version);
+///
+/// if let nbt_rust::NbtTag::Compound(data) = &mut level.root {
+///     data.insert("LevelName".to_string(), "Renamed".to_string().into());
+/// }
+/// std::fs::write("level.dat", level.to_bytes()?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BedrockLevelDat {
+    /// Storage version of the world, which the game checks before loading it
+    pub storage_version: u32,
+    /// Name of the root tag, usually empty
+    pub name: String,
+    /// The root tag, a Compound in any valid file
+    pub root: NbtTag,
+}
+
+impl BedrockLevelDat {
+    /// Creates a `level.dat` with an unnamed root tag
+    pub fn new(storage_version: u32, root: NbtTag) -> Self {
+        Self {
+            storage_version,
+            name: String::new(),
+            root,
+        }
+    }
+
+    /// Reads a `level.dat`, failing if the length in the header does not match the NBT that follows
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let storage_version = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+        if payload.len() != len as usize {
+            return Err(Error::HeaderLengthMismatch {
+                header: len,
+                actual: payload.len(),
+            });
+        }
+
+        let mut decoder = Decoder::new(Cursor::new(&payload)).with_flavor(Bedrock);
+        let (name, root) = decoder.read_root()?;
+
+        let read = decoder.into_inner().position() as usize;
+        if read != payload.len() {
+            return Err(Error::HeaderLengthMismatch {
+                header: len,
+                actual: read,
+            });
+        }
+
+        Ok(Self {
+            storage_version,
+            name,
+            root,
+        })
+    }
+
+    /// Reads a `level.dat` from a byte slice
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_reader(bytes)
+    }
+
+    /// Writes the header followed by the NBT, with the length computed from the NBT
+    pub fn to_writer<W: Write>(&self, mut writer: W) -> Result<()> {
+        let mut ser = Serializer::new(Vec::new()).with_flavor(Bedrock);
+        ser.serialize_tag(&self.name, &self.root)?;
+        let payload = ser.into_inner()?;
+
+        let len = u32::try_from(payload.len())
+            .map_err(|_| Error::Message("level.dat is larger than 4 GiB".to_string()))?;
+
+        writer.write_all(&self.storage_version.to_le_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&payload)?;
+        Ok(())
+    }
+
+    /// Writes the `level.dat` into a byte vector
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.to_writer(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level() -> BedrockLevelDat {
+        let root = NbtTag::from_snbt("{LevelName:\"Bedrock level\",RandomSeed:42L}").unwrap();
+        BedrockLevelDat::new(10, root)
+    }
+
+    /// A `level.dat` whose header declares `len` bytes of payload
+    fn with_len(payload: &[u8], len: u32) -> Vec<u8> {
+        let mut bytes = 10u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn mismatch(bytes: &[u8]) -> (u32, usize) {
+        match BedrockLevelDat::from_bytes(bytes) {
+            Err(Error::HeaderLengthMismatch { header, actual }) => (header, actual),
+            Err(error) => panic!("unexpected error: {error}"),
+            Ok(_) => panic!("a bad header was accepted"),
+        }
+    }
+
+    #[test]
+    fn header_holds_version_and_payload_length() {
+        let bytes = level().to_bytes().unwrap();
+        assert_eq!(bytes[..4], 10u32.to_le_bytes());
+        assert_eq!(bytes[4..8], (bytes.len() as u32 - 8).to_le_bytes());
+
+        let read = BedrockLevelDat::from_bytes(&bytes).unwrap();
+        assert_eq!(read.storage_version, 10);
+        assert_eq!(read.name, "");
+        assert!(read.root == level().root);
+    }
+
+    #[test]
+    fn length_is_recomputed_when_writing() {
+        let mut level = level();
+        let before = level.to_bytes().unwrap();
+        if let NbtTag::Compound(data) = &mut level.root {
+            data.insert("LevelName".to_string(), "Renamed Bedrock level".into());
+        }
+        let after = level.to_bytes().unwrap();
+        assert_eq!(after.len(), before.len() + 8);
+        assert_eq!(after[4..8], (after.len() as u32 - 8).to_le_bytes());
+    }
+
+    #[test]
+    fn header_length_must_match_payload() {
+        let bytes = level().to_bytes().unwrap();
+        let payload = &bytes[8..];
+        let len = payload.len();
+
+        let declared = len as u32 + 1;
+        assert_eq!(mismatch(&with_len(payload, declared)), (declared, len));
+        let declared = len as u32 - 1;
+        assert_eq!(mismatch(&with_len(payload, declared)), (declared, len));
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let bytes = level().to_bytes().unwrap();
+        let len = bytes.len() - 8;
+        let mut payload = bytes[8..].to_vec();
+        payload.extend_from_slice(&[0, 0]);
+
+        // Even when the header counts them, the NBT root ends before the trailing bytes
+        let declared = payload.len() as u32;
+        assert_eq!(mismatch(&with_len(&payload, declared)), (declared, len));
+        assert_eq!(
+            mismatch(&with_len(&payload, len as u32)),
+            (len as u32, len + 2)
+        );
+    }
+
+    #[test]
+    fn truncated_payload_is_rejected() {
+        let bytes = level().to_bytes().unwrap();
+        let len = bytes.len() - 8;
+        assert_eq!(mismatch(&bytes[..bytes.len() - 3]), (len as u32, len - 3));
+
+        // A header matching the truncated payload still fails while decoding it
+        let truncated = &bytes[8..bytes.len() - 3];
+        let error = BedrockLevelDat::from_bytes(&with_len(truncated, len as u32 - 3))
+            .err()
+            .unwrap();
+        assert!(matches!(error.inner(), Error::Io(_)));
+
+        assert!(matches!(
+            BedrockLevelDat::from_bytes(&bytes[..5]).err().unwrap(),
+            Error::Io(_)
+        ));
+    }
+}
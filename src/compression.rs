@@ -0,0 +1,194 @@
+use std::io::{self, Chain, Cursor, Read, Take, Write};
+
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+
+/// Compression applied to NBT data when it is written.
+///
+/// Minecraft gzips whole files (level.dat, player data, structures)
+/// and zlib-compresses chunks stored in region files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Raw, uncompressed NBT
+    #[default]
+    None,
+    /// Gzip with the given compression level (0-9)
+    Gzip(u32),
+    /// Zlib with the given compression level (0-9)
+    Zlib(u32),
+}
+
+/// A reader that transparently decompresses gzip or zlib input
+/// and passes uncompressed input through unchanged
+pub struct CompressedReader<R: Read>(DecompressedInner<R>);
+
+/// The bytes read to detect the compression, followed by the rest of the input
+type Sniffed<R> = Chain<Take<Cursor<[u8; 2]>>, R>;
+
+enum DecompressedInner<R: Read> {
+    None(Sniffed<R>),
+    Gzip(GzDecoder<Sniffed<R>>),
+    Zlib(ZlibDecoder<Sniffed<R>>),
+}
+
+impl<R: Read> CompressedReader<R> {
+    /// Detects the compression from the magic at the start of the input:
+    /// `1f 8b` for gzip, and `78` followed by a byte that makes a valid
+    /// zlib header checksum for zlib. Anything else is read as raw NBT.
+    ///
+    /// At most two bytes are read before decompression starts, so raw
+    /// input is never read past the end of its root tag.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 2];
+        let mut len = 0;
+        while len < magic.len() {
+            match reader.read(&mut magic[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let inner = Cursor::new(magic).take(len as u64).chain(reader);
+
+        Ok(Self(match &magic[..len] {
+            [0x1f, 0x8b] => DecompressedInner::Gzip(GzDecoder::new(inner)),
+            [0x78, check] if (0x7800 | *check as u16).is_multiple_of(31) => {
+                DecompressedInner::Zlib(ZlibDecoder::new(inner))
+            }
+            _ => DecompressedInner::None(inner),
+        }))
+    }
+}
+
+impl<R: Read> Read for CompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            DecompressedInner::None(r) => r.read(buf),
+            DecompressedInner::Gzip(r) => r.read(buf),
+            DecompressedInner::Zlib(r) => r.read(buf),
+        }
+    }
+}
+
+/// A writer that compresses everything written to it
+pub(crate) enum CompressedWriter<W: Write> {
+    None(W),
+    Gzip(GzEncoder<W>),
+    Zlib(ZlibEncoder<W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub(crate) fn new(writer: W, compression: Compression) -> Self {
+        match compression {
+            Compression::None => CompressedWriter::None(writer),
+            Compression::Gzip(level) => {
+                CompressedWriter::Gzip(GzEncoder::new(writer, flate2::Compression::new(level)))
+            }
+            Compression::Zlib(level) => {
+                CompressedWriter::Zlib(ZlibEncoder::new(writer, flate2::Compression::new(level)))
+            }
+        }
+    }
+
+    /// Writes the compressed stream trailer and returns the underlying writer
+    pub(crate) fn finish(self) -> io::Result<W> {
+        match self {
+            CompressedWriter::None(w) => Ok(w),
+            CompressedWriter::Gzip(w) => w.finish(),
+            CompressedWriter::Zlib(w) => w.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::None(w) => w.write(buf),
+            CompressedWriter::Gzip(w) => w.write(buf),
+            CompressedWriter::Zlib(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::None(w) => w.flush(),
+            CompressedWriter::Gzip(w) => w.flush(),
+            CompressedWriter::Zlib(w) => w.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de::from_bytes, error::Error, ser::Serializer, NbtTag};
+
+    fn level() -> NbtTag {
+        NbtTag::from_snbt(r#"{Data:{LevelName:"World",Time:42L,Pos:[I;1,2,3]}}"#).unwrap()
+    }
+
+    fn write(compression: Compression) -> Vec<u8> {
+        let mut ser = Serializer::with_compression(Vec::new(), compression);
+        ser.serialize_tag("level", &level()).unwrap();
+        ser.into_inner().unwrap()
+    }
+
+    #[test]
+    fn round_trips_every_compression() {
+        for compression in [
+            Compression::None,
+            Compression::Gzip(6),
+            Compression::Zlib(6),
+        ] {
+            let bytes = write(compression);
+            let (name, tag) = from_bytes(&bytes).unwrap();
+            assert_eq!(name, "level");
+            assert!(tag == level(), "{compression:?} did not round trip");
+        }
+    }
+
+    #[test]
+    fn detects_magic() {
+        assert_eq!(&write(Compression::Gzip(6))[..2], [0x1f, 0x8b]);
+        assert_eq!(write(Compression::Zlib(1))[0], 0x78);
+        assert!(matches!(
+            CompressedReader::new(write(Compression::Zlib(9)).as_slice())
+                .unwrap()
+                .0,
+            DecompressedInner::Zlib(_)
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_raw_without_full_magic() {
+        // 0x1f alone, or 0x78 with a bad header checksum, is not compressed data
+        for input in [[0x1f, 0x00], [0x78, 0x00]] {
+            let reader = CompressedReader::new(input.as_slice()).unwrap();
+            assert!(matches!(reader.0, DecompressedInner::None(_)));
+            let Err(err) = from_bytes(&input) else {
+                panic!("{input:02x?} was decoded");
+            };
+            assert!(matches!(err.inner(), Error::UnknownTagType(t) if *t == input[0]));
+        }
+    }
+
+    #[test]
+    fn raw_input_is_not_read_past_the_sniffed_bytes() {
+        let mut raw = vec![0x00, 0x01, 0x02];
+        let mut reader = CompressedReader::new(raw.as_slice()).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, raw);
+
+        // A lone End tag is shorter than the magic
+        raw.truncate(1);
+        let (_, tag) = from_bytes(&raw).unwrap();
+        assert!(tag == NbtTag::End);
+    }
+}
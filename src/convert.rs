@@ -0,0 +1,619 @@
+//! Conversion between Rust types and `NbtTag` trees without serde.
+//!
+//! `ToNbt` and `FromNbt` are implemented for the primitives, strings, `Option`, `Vec`,
+//! fixed-size arrays, tuples and maps with string keys, and can be derived for structs
+//! with `#[derive(Nbt)]` (the `macros` feature). Sequences become List tags; wrap them
+//! in `IntArray`, `ByteArray` or `LongArray`, or mark the field `#[nbt(int_array)]`,
+//! to get the array tag types instead.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{
+    error::{Error, Result},
+    nbt_tag::{NbtCompound, NbtList, NbtTag, TagType},
+};
+
+/// Types that can be written as an NBT tag
+pub trait ToNbt {
+    fn to_nbt(&self) -> NbtTag;
+
+    /// The tag stored for a struct field or map entry holding this value,
+    /// or `None` to leave it out, as `Option` does for `None`
+    fn to_nbt_field(&self) -> Option<NbtTag> {
+        Some(self.to_nbt())
+    }
+}
+
+/// Types that can be read from an NBT tag
+pub trait FromNbt: Sized {
+    fn from_nbt(tag: &NbtTag) -> Result<Self>;
+
+    /// The value of a struct field whose tag is missing, or `None` if the tag is required
+    fn from_missing_field() -> Option<Self> {
+        None
+    }
+}
+
+/// Reads the tag with the given name from a compound, reporting a missing
+/// tag and conversion errors with the key
+///
+/// ```
+/// use nbt_rust::{convert::field, NbtTag};
+///
+/// let item = NbtTag::from_snbt(r#"{id:"minecraft:stone",Count:64b}"#).unwrap();
+/// let compound = item.as_compound().unwrap();
+/// let count: i8 = field(compound, "Count").unwrap();
+/// let damage: Option<i32> = field(compound, "Damage").unwrap();
+/// assert_eq!((count, damage), (64, None));
+/// ```
+pub fn field<T: FromNbt>(compound: &NbtCompound, key: &str) -> Result<T> {
+    match compound.get(key) {
+        Some(tag) => T::from_nbt(tag).map_err(|e| e.in_key(key)),
+        None => T::from_missing_field().ok_or_else(|| Error::MissingKey(key.to_string())),
+    }
+}
+
+/// Like `field`, but falls back to `T::default()` if the tag is missing
+pub fn field_or_default<T: FromNbt + Default>(compound: &NbtCompound, key: &str) -> Result<T> {
+    match compound.get(key) {
+        Some(tag) => T::from_nbt(tag).map_err(|e| e.in_key(key)),
+        None => Ok(T::default()),
+    }
+}
+
+/// Returns the compound a struct is read from, failing if the tag is not a compound
+pub fn expect_compound(tag: &NbtTag) -> Result<&NbtCompound> {
+    match tag {
+        NbtTag::Compound(compound) => Ok(compound),
+        tag => Err(type_mismatch(TagType::Compound, tag)),
+    }
+}
+
+/// Adds the entries of a flattened field to the compound of its struct.
+/// Tags other than compounds have no entries and are ignored.
+pub fn flatten_into(compound: &mut NbtCompound, tag: NbtTag) {
+    if let NbtTag::Compound(entries) = tag {
+        compound.extend(entries);
+    }
+}
+
+fn type_mismatch(expected: TagType, tag: &NbtTag) -> Error {
+    Error::TypeMismatch {
+        expected,
+        actual: tag.tag_type(),
+    }
+}
+
+impl ToNbt for NbtTag {
+    fn to_nbt(&self) -> NbtTag {
+        self.clone()
+    }
+}
+
+impl FromNbt for NbtTag {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        Ok(tag.clone())
+    }
+}
+
+impl ToNbt for NbtList {
+    fn to_nbt(&self) -> NbtTag {
+        NbtTag::List(self.clone())
+    }
+}
+
+impl FromNbt for NbtList {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        <&NbtList>::try_from(tag).cloned()
+    }
+}
+
+impl<T: ToNbt + ?Sized> ToNbt for &T {
+    fn to_nbt(&self) -> NbtTag {
+        (**self).to_nbt()
+    }
+
+    fn to_nbt_field(&self) -> Option<NbtTag> {
+        (**self).to_nbt_field()
+    }
+}
+
+impl<T: ToNbt + ?Sized> ToNbt for Box<T> {
+    fn to_nbt(&self) -> NbtTag {
+        (**self).to_nbt()
+    }
+
+    fn to_nbt_field(&self) -> Option<NbtTag> {
+        (**self).to_nbt_field()
+    }
+}
+
+impl<T: FromNbt> FromNbt for Box<T> {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        T::from_nbt(tag).map(Box::new)
+    }
+
+    fn from_missing_field() -> Option<Self> {
+        T::from_missing_field().map(Box::new)
+    }
+}
+
+/// Numbers and bools convert like the `From<T> for NbtTag` impls,
+/// and are read with the `TryFrom<&NbtTag>` impls
+macro_rules! primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl ToNbt for $ty {
+                fn to_nbt(&self) -> NbtTag {
+                    NbtTag::from(*self)
+                }
+            }
+
+            impl FromNbt for $ty {
+                fn from_nbt(tag: &NbtTag) -> Result<Self> {
+                    <$ty>::try_from(tag)
+                }
+            }
+        )*
+    };
+}
+
+primitive!(i8, bool, i16, i32, i64, f32, f64);
+
+impl ToNbt for String {
+    fn to_nbt(&self) -> NbtTag {
+        NbtTag::String(self.clone())
+    }
+}
+
+impl FromNbt for String {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        String::try_from(tag)
+    }
+}
+
+/// Unsigned numbers are stored in the signed tag of the same width, like Minecraft does
+macro_rules! unsigned {
+    ($($ty:ty => $signed:ty),*) => {
+        $(
+            impl ToNbt for $ty {
+                fn to_nbt(&self) -> NbtTag {
+                    NbtTag::from(*self as $signed)
+                }
+            }
+
+            impl FromNbt for $ty {
+                fn from_nbt(tag: &NbtTag) -> Result<Self> {
+                    <$signed>::try_from(tag).map(|v| v as $ty)
+                }
+            }
+        )*
+    };
+}
+
+unsigned!(u8 => i8, u16 => i16, u32 => i32, u64 => i64);
+
+impl ToNbt for str {
+    fn to_nbt(&self) -> NbtTag {
+        NbtTag::String(self.to_string())
+    }
+}
+
+/// `None` is left out of compounds and lists, and is only written as an End tag on its own
+impl<T: ToNbt> ToNbt for Option<T> {
+    fn to_nbt(&self) -> NbtTag {
+        self.to_nbt_field().unwrap_or(NbtTag::End)
+    }
+
+    fn to_nbt_field(&self) -> Option<NbtTag> {
+        self.as_ref().and_then(ToNbt::to_nbt_field)
+    }
+}
+
+/// A missing tag or an End tag is read as `None`
+impl<T: FromNbt> FromNbt for Option<T> {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        match tag {
+            NbtTag::End => Ok(None),
+            tag => T::from_nbt(tag).map(Some),
+        }
+    }
+
+    fn from_missing_field() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Builds a list of the elements, leaving out those that store no tag, like `None`
+///
+/// Panics if two elements convert to different tag types, or one to an End tag,
+/// which only types that convert to more than one tag type, like `NbtTag` itself, can do.
+fn to_list<'a, T: ToNbt + 'a>(elems: impl IntoIterator<Item = &'a T>) -> NbtTag {
+    let mut list = NbtList::new_empty();
+    for (index, elem) in elems.into_iter().enumerate() {
+        let Some(tag) = elem.to_nbt_field() else {
+            continue;
+        };
+        let tag_type = tag.tag_type();
+        if tag_type == TagType::End {
+            panic!("list element {index} is an End tag, which lists cannot hold");
+        }
+        if list.push(tag).is_err() {
+            panic!(
+                "list element {index} is a {tag_type:?} tag, but the list holds {:?} tags",
+                list.elem_type()
+            );
+        }
+    }
+    NbtTag::List(list)
+}
+
+fn from_list<T: FromNbt>(tag: &NbtTag) -> Result<Vec<T>> {
+    <&NbtList>::try_from(tag)?
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| T::from_nbt(tag).map_err(|e| e.in_index(i)))
+        .collect()
+}
+
+fn length_mismatch(expected: usize, actual: usize) -> Error {
+    Error::LengthMismatch { expected, actual }
+}
+
+impl<T: ToNbt> ToNbt for [T] {
+    fn to_nbt(&self) -> NbtTag {
+        to_list(self)
+    }
+}
+
+impl<T: ToNbt> ToNbt for Vec<T> {
+    fn to_nbt(&self) -> NbtTag {
+        to_list(self)
+    }
+}
+
+impl<T: FromNbt> FromNbt for Vec<T> {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        from_list(tag)
+    }
+}
+
+impl<T: ToNbt, const N: usize> ToNbt for [T; N] {
+    fn to_nbt(&self) -> NbtTag {
+        to_list(self)
+    }
+}
+
+impl<T: FromNbt, const N: usize> FromNbt for [T; N] {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        from_list(tag)?
+            .try_into()
+            .map_err(|elems: Vec<T>| length_mismatch(N, elems.len()))
+    }
+}
+
+/// Tuples are lists, so all their elements are of the same type,
+/// and like in other lists, elements that store no tag are left out
+macro_rules! tuple {
+    ($len:literal: $($ty:ident $i:tt),+) => {
+        impl<T: ToNbt> ToNbt for ($($ty,)+) {
+            fn to_nbt(&self) -> NbtTag {
+                to_list([$(&self.$i),+])
+            }
+        }
+
+        impl<T: FromNbt> FromNbt for ($($ty,)+) {
+            fn from_nbt(tag: &NbtTag) -> Result<Self> {
+                let list = <&NbtList>::try_from(tag)?;
+                if list.len() != $len {
+                    return Err(length_mismatch($len, list.len()));
+                }
+                Ok(($(T::from_nbt(&list[$i]).map_err(|e| e.in_index($i))?,)+))
+            }
+        }
+    };
+}
+
+tuple!(1: T 0);
+tuple!(2: T 0, T 1);
+tuple!(3: T 0, T 1, T 2);
+tuple!(4: T 0, T 1, T 2, T 3);
+tuple!(5: T 0, T 1, T 2, T 3, T 4);
+tuple!(6: T 0, T 1, T 2, T 3, T 4, T 5);
+tuple!(7: T 0, T 1, T 2, T 3, T 4, T 5, T 6);
+tuple!(8: T 0, T 1, T 2, T 3, T 4, T 5, T 6, T 7);
+
+fn to_compound<'a, K, V>(entries: impl IntoIterator<Item = (&'a K, &'a V)>) -> NbtTag
+where
+    K: AsRef<str> + 'a,
+    V: ToNbt + 'a,
+{
+    NbtTag::Compound(
+        entries
+            .into_iter()
+            .filter_map(|(k, v)| Some((k.as_ref().to_string(), v.to_nbt_field()?)))
+            .collect(),
+    )
+}
+
+fn from_compound<K: From<String>, V: FromNbt, M: FromIterator<(K, V)>>(tag: &NbtTag) -> Result<M> {
+    expect_compound(tag)?
+        .iter()
+        .map(|(k, tag)| {
+            let v = V::from_nbt(tag).map_err(|e| e.in_key(k))?;
+            Ok((K::from(k.clone()), v))
+        })
+        .collect()
+}
+
+impl<K: AsRef<str>, V: ToNbt, S> ToNbt for HashMap<K, V, S> {
+    fn to_nbt(&self) -> NbtTag {
+        to_compound(self)
+    }
+}
+
+impl<K, V, S> FromNbt for HashMap<K, V, S>
+where
+    K: From<String> + Eq + Hash,
+    V: FromNbt,
+    S: BuildHasher + Default,
+{
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        from_compound(tag)
+    }
+}
+
+impl<K: AsRef<str>, V: ToNbt> ToNbt for BTreeMap<K, V> {
+    fn to_nbt(&self) -> NbtTag {
+        to_compound(self)
+    }
+}
+
+impl<K: From<String> + Ord, V: FromNbt> FromNbt for BTreeMap<K, V> {
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        from_compound(tag)
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<K: AsRef<str>, V: ToNbt, S> ToNbt for indexmap::IndexMap<K, V, S> {
+    fn to_nbt(&self) -> NbtTag {
+        to_compound(self)
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<K, V, S> FromNbt for indexmap::IndexMap<K, V, S>
+where
+    K: From<String> + Eq + Hash,
+    V: FromNbt,
+    S: BuildHasher + Default,
+{
+    fn from_nbt(tag: &NbtTag) -> Result<Self> {
+        from_compound(tag)
+    }
+}
+
+/// Values that can be written as an array tag: `Vec<E>`, `[E; N]`, `[E]`,
+/// a reference to any of them, and an `Option` of one
+pub trait ArrayElems<E> {
+    /// The elements to write, or `None` to leave the field out
+    fn elems(&self) -> Option<&[E]>;
+}
+
+impl<E> ArrayElems<E> for Vec<E> {
+    fn elems(&self) -> Option<&[E]> {
+        Some(self)
+    }
+}
+
+impl<E, const N: usize> ArrayElems<E> for [E; N] {
+    fn elems(&self) -> Option<&[E]> {
+        Some(self)
+    }
+}
+
+impl<E> ArrayElems<E> for [E] {
+    fn elems(&self) -> Option<&[E]> {
+        Some(self)
+    }
+}
+
+impl<E, T: ArrayElems<E> + ?Sized> ArrayElems<E> for &T {
+    fn elems(&self) -> Option<&[E]> {
+        (**self).elems()
+    }
+}
+
+impl<E, T: ArrayElems<E>> ArrayElems<E> for Option<T> {
+    fn elems(&self) -> Option<&[E]> {
+        self.as_ref().and_then(ArrayElems::elems)
+    }
+}
+
+/// Field types that can be read from an array tag: `Vec<E>`, `[E; N]`
+/// and an `Option` of either
+pub trait ArrayField<E>: ArrayElems<E> + Sized {
+    fn from_elems(elems: &[E]) -> Result<Self>;
+
+    /// See `FromNbt::from_missing_field`
+    fn from_missing_field() -> Option<Self> {
+        None
+    }
+}
+
+impl<E: Clone> ArrayField<E> for Vec<E> {
+    fn from_elems(elems: &[E]) -> Result<Self> {
+        Ok(elems.to_vec())
+    }
+}
+
+impl<E: Copy, const N: usize> ArrayField<E> for [E; N] {
+    fn from_elems(elems: &[E]) -> Result<Self> {
+        elems
+            .try_into()
+            .map_err(|_| length_mismatch(N, elems.len()))
+    }
+}
+
+impl<E, T: ArrayField<E>> ArrayField<E> for Option<T> {
+    fn from_elems(elems: &[E]) -> Result<Self> {
+        T::from_elems(elems).map(Some)
+    }
+
+    fn from_missing_field() -> Option<Self> {
+        Some(None)
+    }
+}
+
+macro_rules! array_wrapper {
+    ($(#[$doc:meta])* $name:ident($elem:ty)) => {
+        $(#[$doc])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+        pub struct $name<T>(pub T);
+
+        impl<T: ArrayElems<$elem>> ToNbt for $name<T> {
+            fn to_nbt(&self) -> NbtTag {
+                self.to_nbt_field().unwrap_or(NbtTag::End)
+            }
+
+            fn to_nbt_field(&self) -> Option<NbtTag> {
+                self.0.elems().map(|e| NbtTag::$name(e.to_vec()))
+            }
+        }
+
+        impl<T: ArrayField<$elem>> FromNbt for $name<T> {
+            fn from_nbt(tag: &NbtTag) -> Result<Self> {
+                match tag {
+                    NbtTag::$name(elems) => T::from_elems(elems).map($name),
+                    tag => Err(type_mismatch(TagType::$name, tag)),
+                }
+            }
+
+            fn from_missing_field() -> Option<Self> {
+                T::from_missing_field().map($name)
+            }
+        }
+    };
+}
+
+array_wrapper! {
+    /// Converts a sequence of bytes to and from a ByteArray tag instead of a List.
+    /// Writing also accepts a reference, as in `ByteArray(&bytes).to_nbt()`.
+    ByteArray(u8)
+}
+
+array_wrapper! {
+    /// Converts a sequence of ints to and from an IntArray tag instead of a List.
+    /// Writing also accepts a reference, as in `IntArray(&ints).to_nbt()`.
+    ///
+    /// ```
+    /// use nbt_rust::{
+    ///     convert::{FromNbt, IntArray, ToNbt},
+    ///     NbtTag,
+    /// };
+    ///
+    /// let uuid = IntArray([1, 2, 3, 4]);
+    /// let tag = uuid.to_nbt();
+    /// assert_eq!(tag.as_int_array(), Some(&uuid.0[..]));
+    /// assert_eq!(IntArray::from_nbt(&tag).unwrap(), uuid);
+    /// assert!(IntArray(&uuid.0[..2]).to_nbt() == NbtTag::IntArray(vec![1, 2]));
+    /// ```
+    IntArray(i32)
+}
+
+array_wrapper! {
+    /// Converts a sequence of longs to and from a LongArray tag instead of a List.
+    /// Writing also accepts a reference, as in `LongArray(&longs).to_nbt()`.
+    LongArray(i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn array_wrappers_round_trip() {
+        let longs = LongArray(vec![i64::MIN, 0, 7]);
+        let tag = longs.to_nbt();
+        assert!(tag == NbtTag::LongArray(vec![i64::MIN, 0, 7]));
+        assert_eq!(LongArray::from_nbt(&tag).unwrap(), longs);
+        assert!(LongArray(&longs.0).to_nbt() == tag);
+        assert!(LongArray(&longs.0[1..]).to_nbt() == NbtTag::LongArray(vec![0, 7]));
+
+        let bytes = ByteArray([1u8, 255]);
+        assert_eq!(ByteArray::from_nbt(&bytes.to_nbt()).unwrap(), bytes);
+
+        let error = IntArray::<[i32; 4]>::from_nbt(&NbtTag::IntArray(vec![1]))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.inner(),
+            Error::LengthMismatch {
+                expected: 4,
+                actual: 1
+            }
+        ));
+        let error = IntArray::<Vec<i32>>::from_nbt(&NbtTag::LongArray(vec![]))
+            .err()
+            .unwrap();
+        assert!(matches!(error.inner(), Error::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn optional_arrays_are_left_out_when_none() {
+        let none = IntArray(None::<Vec<i32>>);
+        assert!(none.to_nbt_field().is_none());
+        assert_eq!(IntArray::from_missing_field(), Some(none));
+
+        let some = IntArray(Some([1, 2]));
+        let tag = some.to_nbt_field().unwrap();
+        assert!(tag == NbtTag::IntArray(vec![1, 2]));
+        assert_eq!(IntArray::from_nbt(&tag).unwrap(), some);
+        assert_eq!(IntArray::<Vec<i32>>::from_missing_field(), None);
+    }
+
+    #[test]
+    fn none_is_left_out_of_lists() {
+        let tag = vec![Some(1), None, Some(3)].to_nbt();
+        let list = tag.as_list().unwrap();
+        assert_eq!(list.elem_type(), TagType::Int);
+        assert!(list[..] == [NbtTag::Int(1), NbtTag::Int(3)]);
+
+        let empty = [None::<i8>, None].to_nbt();
+        assert_eq!(empty.as_list().unwrap().elem_type(), TagType::End);
+        assert!(empty.as_list().unwrap().is_empty());
+
+        let pair = (Some(1.5), None).to_nbt();
+        assert_eq!(pair.as_list().unwrap().len(), 1);
+        assert!(None::<i32>.to_nbt() == NbtTag::End);
+    }
+
+    #[test]
+    fn tuples_are_lists_of_one_type() {
+        let pos = (0.5, 64.0, -3.5).to_nbt();
+        assert_eq!(pos.as_list().unwrap().elem_type(), TagType::Double);
+        assert_eq!(
+            <(f64, f64, f64)>::from_nbt(&pos).unwrap(),
+            (0.5, 64.0, -3.5)
+        );
+
+        let names = ("a", "b").to_nbt();
+        assert_eq!(names.as_list().unwrap().elem_type(), TagType::String);
+    }
+
+    #[test]
+    #[should_panic(expected = "list element 1 is a String tag, but the list holds Int tags")]
+    fn mixed_tags_are_not_made_into_a_list() {
+        vec![NbtTag::Int(1), NbtTag::String("a".to_string())].to_nbt();
+    }
+
+    #[test]
+    #[should_panic(expected = "list element 0 is an End tag")]
+    fn end_tags_are_not_made_into_a_list() {
+        (NbtTag::End,).to_nbt();
+    }
+}
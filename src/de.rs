@@ -1,34 +1,30 @@
 use std::{
-    collections::HashMap,
-    io::{Cursor, Read},
+    io::{self, Cursor, Read},
+    marker::PhantomData,
 };
 
+mod events;
+#[cfg(feature = "serde")]
+pub(crate) mod serde_impl;
+pub use events::{ArrayChunk, NbtEvent, NbtEventReader, Primitive};
+#[cfg(feature = "serde")]
+pub use serde_impl::Deserializer;
+
 use crate::{
-    error::{Result},
-    nbt_tag::TagType,
+    compression::CompressedReader,
+    error::{Error, Result},
+    flavor::{Java, NbtFlavor, ReadBytes},
+    mutf8::StringEncoding,
+    nbt_tag::{NbtList, TagType},
     NbtTag,
 };
 
 /// Reads a single NBT tag from a reader
+///
+/// Gzip and zlib compressed input is detected by its magic bytes
+/// and decompressed transparently.
 pub fn from_reader<R: Read>(reader: &mut R) -> Result<(String, NbtTag)> {
-    let (prefix, name) = read_tag_header(reader)?;
-    let value = match prefix {
-        TagType::Byte => read_headless_byte(reader)?,
-        TagType::Short => read_headless_short(reader)?,
-        TagType::Int => read_headless_int(reader)?,
-        TagType::Long => read_headless_long(reader)?,
-        TagType::Float => read_headless_float(reader)?,
-        TagType::Double => read_headless_double(reader)?,
-        TagType::ByteArray => read_headless_byte_array(reader)?,
-        TagType::String => read_headless_string(reader)?,
-        TagType::List => read_headless_list(reader)?,
-        TagType::Compound => read_headless_compound(reader)?,
-        TagType::IntArray => read_headless_int_array(reader)?,
-        TagType::LongArray => read_headless_long_array(reader)?,
-        TagType::End => NbtTag::End,
-    };
-
-    Ok((name, value))
+    from_reader_flavored(reader, Java)
 }
 
 /// Reads a single NBT tag from a byte slice
@@ -37,177 +33,769 @@ pub fn from_bytes(bytes: &[u8]) -> Result<(String, NbtTag)> {
     from_reader(&mut reader)
 }
 
-fn read_tag_header<R: Read>(reader: &mut R) -> Result<(TagType, String)> {
-    let mut prefix = [0; 1];
-    reader.read_exact(&mut prefix)?;
-    let prefix: TagType = prefix[0].try_into()?;
+/// Reads a single NBT tag of the given flavor from a reader
+///
+/// Like `from_reader`, compressed input is decompressed transparently.
+///
+/// ```no_run
+/// use nbt_rust::{de::from_reader_flavored, flavor::Bedrock};
+///
+/// # fn main() -> nbt_rust::error::Result<()> {
+/// let mut file = std::fs::File::open("structure.mcstructure")?;
+/// let (name, tag) = from_reader_flavored(&mut file, Bedrock)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_reader_flavored<R: Read, F: NbtFlavor>(
+    reader: &mut R,
+    flavor: F,
+) -> Result<(String, NbtTag)> {
+    Decoder::new(CompressedReader::new(reader)?)
+        .with_flavor(flavor)
+        .read_root()
+}
 
-    if prefix == TagType::End {
-        return Ok((prefix, String::new()));
-    }
+/// Reads a single NBT tag of the given flavor from a byte slice
+pub fn from_bytes_flavored<F: NbtFlavor>(bytes: &[u8], flavor: F) -> Result<(String, NbtTag)> {
+    let mut reader = Cursor::new(bytes);
+    from_reader_flavored(&mut reader, flavor)
+}
 
-    let mut name_len = [0; 2];
-    reader.read_exact(&mut name_len)?;
-    let name_len = u16::from_be_bytes(name_len);
+/// Bounds on what the decoder accepts, protecting against
+/// hostile or corrupt input that would otherwise exhaust memory or the stack
+///
+/// The byte quota is charged like Minecraft's `NbtAccounter`: besides the bytes
+/// read, every tag, list element and compound entry costs a fixed overhead
+/// approximating the memory it takes once decoded, so a flood of tiny tags
+/// cannot build a tree much larger than the quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// Maximum nesting depth of lists and compounds
+    pub max_depth: usize,
+    /// Maximum number of bytes charged for a single root tag
+    pub max_bytes: u64,
+    /// Maximum number of elements in a single list or array
+    pub max_len: usize,
+}
+
+impl ReadLimits {
+    /// No limits besides the nesting depth, which still protects the stack.
+    /// Only meant for trusted input
+    pub const fn unlimited() -> Self {
+        Self {
+            max_depth: 512,
+            max_bytes: u64::MAX,
+            max_len: i32::MAX as usize,
+        }
+    }
 
-    let mut name = vec![0; name_len as usize];
-    reader.read_exact(&mut name)?;
-    let name = String::from_utf8(name)?;
+    /// The quota Minecraft applies to NBT files it reads (100 MiB)
+    pub const fn file() -> Self {
+        Self {
+            max_bytes: 100 * 1024 * 1024,
+            ..Self::unlimited()
+        }
+    }
 
-    Ok((prefix, name))
+    /// The quota Minecraft applies to NBT received over the network (2 MiB)
+    pub const fn network() -> Self {
+        Self {
+            max_bytes: 2 * 1024 * 1024,
+            ..Self::unlimited()
+        }
+    }
 }
 
-#[inline]
-fn read_headless_byte<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut byte = [0; 1];
-    reader.read_exact(&mut byte)?;
+/// The quota of Minecraft's file reading, `ReadLimits::file()`
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self::file()
+    }
+}
 
-    Ok(NbtTag::Byte(byte[0]))
+/// Quota charged for each compound entry on top of its name and value,
+/// as `NbtAccounter` does
+pub(crate) const ENTRY_OVERHEAD: u64 = 28;
+/// Quota charged for each string on top of its bytes, which Minecraft
+/// counts twice as it stores them as UTF-16
+pub(crate) const STRING_OVERHEAD: u64 = 36;
+/// Quota charged for each list element for its reference in the list
+pub(crate) const ELEMENT_OVERHEAD: u64 = 4;
+
+/// Quota charged for a tag of the given type on top of the bytes read for it,
+/// the sizes `NbtAccounter` charges minus the payload. Strings are charged
+/// when their length is read
+pub(crate) const fn tag_overhead(tag_type: TagType) -> u64 {
+    match tag_type {
+        TagType::End | TagType::String => 0,
+        TagType::Byte
+        | TagType::Short
+        | TagType::Int
+        | TagType::Long
+        | TagType::Float
+        | TagType::Double => 8,
+        TagType::ByteArray | TagType::IntArray | TagType::LongArray => 24,
+        TagType::List => 37,
+        TagType::Compound => 48,
+    }
 }
 
-#[inline]
-fn read_headless_short<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut short = [0; 2];
-    reader.read_exact(&mut short)?;
+/// Reads NBT tags from a reader, enforcing the configured `ReadLimits`
+///
+/// ```no_run
+/// use nbt_rust::{compression::CompressedReader, de::{Decoder, ReadLimits}};
+///
+/// # fn main() -> nbt_rust::error::Result<()> {
+/// # let socket = std::io::empty();
+/// let (name, tag) = Decoder::new(CompressedReader::new(socket)?)
+///     .with_limits(ReadLimits::network())
+///     .read_root()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Decoder<R, F = Java> {
+    reader: R,
+    limits: ReadLimits,
+    flavor: PhantomData<F>,
+    encoding: StringEncoding,
+    depth: usize,
+    bytes_read: u64,
+    /// Bytes charged against `limits.max_bytes`, see `ReadLimits`
+    charged: u64,
+}
 
-    Ok(NbtTag::Short(i16::from_be_bytes(short)))
+impl<R: Read> Decoder<R> {
+    /// Creates a decoder reading uncompressed Java NBT with the default limits
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            limits: ReadLimits::default(),
+            flavor: PhantomData,
+            encoding: Java::STRING_ENCODING,
+            depth: 0,
+            bytes_read: 0,
+            charged: 0,
+        }
+    }
 }
 
-#[inline]
-fn read_headless_int<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut int = [0; 4];
-    reader.read_exact(&mut int)?;
+impl<R: Read, F: NbtFlavor> Decoder<R, F> {
+    /// Replaces the limits enforced by the decoder
+    pub fn with_limits(mut self, limits: ReadLimits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-    Ok(NbtTag::Int(i32::from_be_bytes(int)))
-}
+    /// Selects the flavor of NBT to read, Java by default.
+    /// This also selects the string encoding of that flavor
+    pub fn with_flavor<G: NbtFlavor>(self, _flavor: G) -> Decoder<R, G> {
+        Decoder {
+            reader: self.reader,
+            limits: self.limits,
+            flavor: PhantomData,
+            encoding: G::STRING_ENCODING,
+            depth: self.depth,
+            bytes_read: self.bytes_read,
+            charged: self.charged,
+        }
+    }
 
-#[inline]
-fn read_headless_long<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut long = [0; 8];
-    reader.read_exact(&mut long)?;
+    /// Replaces the encoding strings are read with, Modified UTF-8 by default
+    pub fn with_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
 
-    Ok(NbtTag::Long(i64::from_be_bytes(long)))
-}
+    /// Consumes the decoder and returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 
-#[inline]
-fn read_headless_float<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut float = [0; 4];
-    reader.read_exact(&mut float)?;
+    /// Reads a root tag and its name, which is empty if the flavor has none
+    ///
+    /// Errors carry the byte offset and the path of the tag being read.
+    pub fn read_root(&mut self) -> Result<(String, NbtTag)> {
+        if !F::HAS_ROOT_NAME {
+            return Ok((String::new(), self.read_unnamed()?));
+        }
 
-    Ok(NbtTag::Float(f32::from_be_bytes(float)))
-}
+        self.start_root();
+        let (prefix, name) = self.read_tag_header().map_err(|e| self.locate(e))?;
+        let value = self.read_headless_tag(prefix).map_err(|e| self.locate(e))?;
 
-#[inline]
-fn read_headless_double<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut double = [0; 8];
-    reader.read_exact(&mut double)?;
+        Ok((name, value))
+    }
 
-    Ok(NbtTag::Double(f64::from_be_bytes(double)))
-}
+    /// Reads a root tag without a name, as sent by Java Edition since 1.20.2
+    /// (protocol 764): just the type byte followed by the payload
+    pub fn read_unnamed(&mut self) -> Result<NbtTag> {
+        self.start_root();
+        let prefix = self.read_entry_type().map_err(|e| self.locate(e))?;
+        self.read_headless_tag(prefix).map_err(|e| self.locate(e))
+    }
 
-#[inline]
-fn read_headless_byte_array<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut len = [0; 4];
-    reader.read_exact(&mut len)?;
-    let len = i32::from_be_bytes(len);
+    fn read_tag_header(&mut self) -> Result<(TagType, String)> {
+        let prefix = self.read_entry_type()?;
+        if prefix == TagType::End {
+            return Ok((prefix, String::new()));
+        }
 
-    let mut bytes = vec![0; len as usize];
-    reader.read_exact(&mut bytes)?;
+        let name = self.read_string()?;
+        Ok((prefix, name))
+    }
 
-    Ok(NbtTag::ByteArray(bytes))
-}
+    #[inline]
+    fn read_headless_byte(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::Byte(self.read_i8()?))
+    }
 
-fn read_headless_string<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut len = [0; 2];
-    reader.read_exact(&mut len)?;
-    let len = u16::from_be_bytes(len);
+    #[inline]
+    fn read_headless_short(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::Short(self.read_i16()?))
+    }
 
-    let mut bytes = vec![0; len as usize];
-    reader.read_exact(&mut bytes)?;
-    let string = String::from_utf8(bytes)?;
+    #[inline]
+    fn read_headless_int(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::Int(self.read_i32()?))
+    }
 
-    Ok(NbtTag::String(string))
-}
+    #[inline]
+    fn read_headless_long(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::Long(self.read_i64()?))
+    }
+
+    #[inline]
+    fn read_headless_float(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::Float(self.read_f32()?))
+    }
+
+    #[inline]
+    fn read_headless_double(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::Double(self.read_f64()?))
+    }
+
+    #[inline]
+    fn read_headless_byte_array(&mut self) -> Result<NbtTag> {
+        let len = self.read_len()?;
+        Ok(NbtTag::ByteArray(self.read_bytes(len)?))
+    }
+
+    fn read_headless_string(&mut self) -> Result<NbtTag> {
+        Ok(NbtTag::String(self.read_string()?))
+    }
+
+    fn read_headless_list(&mut self) -> Result<NbtTag> {
+        let (prefix, len) = self.read_list_header()?;
+
+        let tags = self.nested(|de| {
+            (0..len)
+                .map(|i| de.read_headless_tag(prefix).map_err(|e| e.in_index(i)))
+                .collect()
+        })?;
+
+        Ok(NbtTag::List(NbtList::with_tags(prefix, tags)?))
+    }
+
+    fn read_headless_compound(&mut self) -> Result<NbtTag> {
+        let tags = self.nested(|de| {
+            let mut tags: Vec<(String, NbtTag)> = Vec::new();
+            loop {
+                let (prefix, name) = de.read_tag_header()?;
+                if prefix == TagType::End {
+                    return Ok(tags);
+                }
+
+                let value = de.read_headless_tag(prefix).map_err(|e| e.in_key(&name))?;
+                tags.push((name, value));
+            }
+        })?;
+
+        Ok(NbtTag::Compound(tags.into_iter().collect()))
+    }
 
-fn read_headless_list<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut prefix = [0; 1];
-    reader.read_exact(&mut prefix)?;
-    let prefix: TagType = prefix[0].try_into()?;
+    fn read_headless_int_array(&mut self) -> Result<NbtTag> {
+        let len = self.read_len()?;
+        Ok(NbtTag::IntArray(F::read_int_array(self, len)?))
+    }
 
-    let mut len = [0; 4];
-    reader.read_exact(&mut len)?;
-    let len = i32::from_be_bytes(len);
+    fn read_headless_long_array(&mut self) -> Result<NbtTag> {
+        let len = self.read_len()?;
+        Ok(NbtTag::LongArray(F::read_long_array(self, len)?))
+    }
 
-    let mut tags = Vec::new();
-    for _ in 0..len {
-        tags.push(read_headless_tag(reader, prefix)?);
+    fn read_headless_tag(&mut self, prefix: TagType) -> Result<NbtTag> {
+        match prefix {
+            TagType::Byte => self.read_headless_byte(),
+            TagType::Short => self.read_headless_short(),
+            TagType::Int => self.read_headless_int(),
+            TagType::Long => self.read_headless_long(),
+            TagType::Float => self.read_headless_float(),
+            TagType::Double => self.read_headless_double(),
+            TagType::ByteArray => self.read_headless_byte_array(),
+            TagType::String => self.read_headless_string(),
+            TagType::List => self.read_headless_list(),
+            TagType::Compound => self.read_headless_compound(),
+            TagType::IntArray => self.read_headless_int_array(),
+            TagType::LongArray => self.read_headless_long_array(),
+            TagType::End => Ok(NbtTag::End),
+        }
     }
 
-    Ok(NbtTag::List(tags))
+    /// Discards the payload of a tag without allocating
+    fn skip(&mut self, tag_type: TagType) -> Result<()> {
+        match tag_type {
+            TagType::End => Ok(()),
+            TagType::Byte => self.skip_bytes(1),
+            // Numbers may be VarInts, so they are read rather than skipped
+            TagType::Short => self.read_i16().map(drop),
+            TagType::Int => self.read_i32().map(drop),
+            TagType::Long => self.read_i64().map(drop),
+            TagType::Float => self.read_f32().map(drop),
+            TagType::Double => self.read_f64().map(drop),
+            TagType::ByteArray => {
+                let len = self.read_len()?;
+                self.skip_bytes(len as u64)
+            }
+            TagType::IntArray => {
+                let len = self.read_len()?;
+                F::skip_int_array(self, len)
+            }
+            TagType::LongArray => {
+                let len = self.read_len()?;
+                F::skip_long_array(self, len)
+            }
+            TagType::String => {
+                let len = self.read_string_len()?;
+                self.skip_bytes(len as u64)
+            }
+            TagType::List => {
+                let (elem_type, len) = self.read_list_header()?;
+
+                self.nested(|de| {
+                    (0..len).try_for_each(|i| de.skip(elem_type).map_err(|e| e.in_index(i)))
+                })
+            }
+            TagType::Compound => self.nested(Self::skip_compound_entries),
+        }
+    }
+
+    /// Discards the remaining tags of a compound up to and including its End tag
+    fn skip_compound_entries(&mut self) -> Result<()> {
+        loop {
+            let tag_type = self.read_entry_type()?;
+            if tag_type == TagType::End {
+                return Ok(());
+            }
+            let name_len = self.read_string_len()?;
+            self.skip_bytes(name_len as u64)?;
+            self.skip(tag_type)?;
+        }
+    }
 }
 
-fn read_headless_compound<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut tags: Vec<(String, NbtTag)> = Vec::new();
+/// Limit bookkeeping
+impl<R: Read, F: NbtFlavor> Decoder<R, F> {
+    /// Enters a list or compound, failing if that nests too deep
+    #[inline]
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::DepthLimitExceeded(self.limits.max_depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        self.depth -= 1;
+    }
 
-    loop {
-        let (prefix, name) = read_tag_header(reader)?;
-        if prefix == TagType::End {
-            break;
+    /// Runs `f` inside a list or compound, restoring the depth even if it fails
+    #[inline]
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.enter()?;
+        let result = f(self);
+        self.exit();
+        result
+    }
+
+    /// Resets the bookkeeping before reading a root tag
+    fn start_root(&mut self) {
+        self.depth = 0;
+        self.bytes_read = 0;
+        self.charged = 0;
+    }
+
+    /// Charges `len` bytes against the quota, failing once it is exceeded
+    #[inline]
+    fn check_quota(&mut self, len: u64) -> Result<()> {
+        match self.charged.checked_add(len) {
+            Some(total) if total <= self.limits.max_bytes => {
+                self.charged = total;
+                Ok(())
+            }
+            _ => Err(Error::ByteLimitExceeded(self.limits.max_bytes)),
         }
+    }
 
-        let value = read_headless_tag(reader, prefix)?;
+    /// Reads the type of a root tag or compound entry, charging the
+    /// overhead of the entry and its value
+    fn read_entry_type(&mut self) -> Result<TagType> {
+        let tag_type = self.read_tag_type()?;
+        if tag_type != TagType::End {
+            self.check_quota(ENTRY_OVERHEAD + tag_overhead(tag_type))?;
+        }
+        Ok(tag_type)
+    }
 
-        tags.push((name, value));
+    /// Reads the element type and length of a list, charging the
+    /// overhead of all its elements up front
+    fn read_list_header(&mut self) -> Result<(TagType, usize)> {
+        let elem_type = self.read_tag_type()?;
+        let len = self.read_len()?;
+        if elem_type == TagType::End && len > 0 {
+            return Err(Error::Unsupported("List of End tags"));
+        }
+
+        self.check_quota(len as u64 * (ELEMENT_OVERHEAD + tag_overhead(elem_type)))?;
+        Ok((elem_type, len))
     }
 
-    let tags: HashMap<String, NbtTag> = tags.into_iter().collect();
-    Ok(NbtTag::Compound(tags))
+    /// Attaches the current byte offset to an error
+    fn locate(&self, error: Error) -> Error {
+        error.at_offset(self.bytes_read)
+    }
 }
 
-fn read_headless_int_array<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut len = [0; 4];
-    reader.read_exact(&mut len)?;
-    let len = i32::from_be_bytes(len);
+/// Primitive readers shared by the tag reader and the serde deserializer
+impl<R: Read, F: NbtFlavor> Decoder<R, F> {
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.check_quota(buf.len() as u64)?;
+        self.reader.read_exact(buf)?;
+        self.bytes_read += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Reads `len` bytes, growing the buffer only as data actually arrives
+    /// so a bogus length cannot trigger a huge allocation up front
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
+        self.check_quota(len as u64)?;
+
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.bytes_read += len as u64;
+        Ok(bytes)
+    }
+
+    fn skip_bytes(&mut self, len: u64) -> Result<()> {
+        self.check_quota(len)?;
+
+        let skipped = io::copy(&mut (&mut self.reader).take(len), &mut io::sink())?;
+        if skipped < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.bytes_read += len;
+        Ok(())
+    }
+
+    #[inline]
+    fn read_tag_type(&mut self) -> Result<TagType> {
+        self.read_u8()?.try_into()
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut byte = [0; 1];
+        self.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    #[inline]
+    fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    #[inline]
+    fn read_i16(&mut self) -> Result<i16> {
+        F::read_short(self)
+    }
+
+    #[inline]
+    fn read_i32(&mut self) -> Result<i32> {
+        F::read_int(self)
+    }
+
+    #[inline]
+    fn read_i64(&mut self) -> Result<i64> {
+        F::read_long(self)
+    }
+
+    #[inline]
+    fn read_f32(&mut self) -> Result<f32> {
+        F::read_float(self)
+    }
 
-    let mut ints = Vec::new();
-    for _ in 0..len {
-        let mut int = [0; 4];
-        reader.read_exact(&mut int)?;
-        ints.push(i32::from_be_bytes(int));
+    #[inline]
+    fn read_f64(&mut self) -> Result<f64> {
+        F::read_double(self)
     }
 
-    Ok(NbtTag::IntArray(ints))
+    /// Reads the i32 length prefix of a list or array, rejecting negative
+    /// lengths and lengths above the configured limit
+    #[inline]
+    fn read_len(&mut self) -> Result<usize> {
+        let len = self.read_i32()?;
+        if len < 0 {
+            return Err(Error::NegativeLength(len));
+        }
+
+        let len = len as usize;
+        if len > self.limits.max_len {
+            return Err(Error::LengthLimitExceeded(self.limits.max_len));
+        }
+        Ok(len)
+    }
+
+    /// Reads the length of a string, charging the string's overhead
+    #[inline]
+    fn read_string_len(&mut self) -> Result<usize> {
+        let len = F::read_string_len(self)?;
+        self.check_quota(STRING_OVERHEAD + len as u64)?;
+        Ok(len)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let len = self.read_string_len()?;
+        let bytes = self.read_bytes(len)?;
+        self.encoding.decode(bytes)
+    }
 }
 
-fn read_headless_long_array<R: Read>(reader: &mut R) -> Result<NbtTag> {
-    let mut len = [0; 4];
-    reader.read_exact(&mut len)?;
-    let len = i32::from_be_bytes(len);
+impl<R: Read, F: NbtFlavor> ReadBytes for Decoder<R, F> {
+    #[inline]
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.read_exact(buf)
+    }
 
-    let mut longs = Vec::new();
-    for _ in 0..len {
-        let mut long = [0; 8];
-        reader.read_exact(&mut long)?;
-        longs.push(i64::from_be_bytes(long));
+    #[inline]
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>> {
+        self.read_bytes(len)
     }
 
-    Ok(NbtTag::LongArray(longs))
+    #[inline]
+    fn skip(&mut self, len: u64) -> Result<()> {
+        self.skip_bytes(len)
+    }
 }
 
-fn read_headless_tag<R: Read>(reader: &mut R, prefix: TagType) -> Result<NbtTag> {
-    match prefix {
-        TagType::Byte => read_headless_byte(reader),
-        TagType::Short => read_headless_short(reader),
-        TagType::Int => read_headless_int(reader),
-        TagType::Long => read_headless_long(reader),
-        TagType::Float => read_headless_float(reader),
-        TagType::Double => read_headless_double(reader),
-        TagType::ByteArray => read_headless_byte_array(reader),
-        TagType::String => read_headless_string(reader),
-        TagType::List => read_headless_list(reader),
-        TagType::Compound => read_headless_compound(reader),
-        TagType::IntArray => read_headless_int_array(reader),
-        TagType::LongArray => read_headless_long_array(reader),
-        TagType::End => Ok(NbtTag::End),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A root compound named "" holding a single entry named "v"
+    fn entry(tag_type: TagType, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![TagType::Compound as u8, 0, 0, tag_type as u8, 0, 1, b'v'];
+        bytes.extend_from_slice(payload);
+        bytes.push(TagType::End as u8);
+        bytes
+    }
+
+    /// `depth` lists nested in each other, the innermost one empty
+    fn nested_lists(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![TagType::List as u8, 0, 0];
+        for _ in 1..depth {
+            bytes.extend_from_slice(&[TagType::List as u8, 0, 0, 0, 1]);
+        }
+        bytes.extend_from_slice(&[TagType::End as u8, 0, 0, 0, 0]);
+        bytes
+    }
+
+    fn read(bytes: &[u8], limits: ReadLimits) -> Result<(String, NbtTag)> {
+        Decoder::new(bytes).with_limits(limits).read_root()
+    }
+
+    fn read_err(bytes: &[u8], limits: ReadLimits) -> Error {
+        let Err(error) = read(bytes, limits) else {
+            panic!("decoding succeeded");
+        };
+        error
+    }
+
+    #[test]
+    fn default_limits_are_bounded() {
+        assert_eq!(ReadLimits::default(), ReadLimits::file());
+        assert_eq!(ReadLimits::default().max_bytes, 100 * 1024 * 1024);
+        assert_eq!(ReadLimits::network().max_bytes, 2 * 1024 * 1024);
+        assert_eq!(ReadLimits::unlimited().max_bytes, u64::MAX);
+    }
+
+    #[test]
+    fn rejects_negative_lengths() {
+        for tag_type in [TagType::ByteArray, TagType::IntArray, TagType::LongArray] {
+            let error = read_err(
+                &entry(tag_type, &(-1i32).to_be_bytes()),
+                ReadLimits::default(),
+            );
+            assert!(matches!(error.inner(), Error::NegativeLength(-1)));
+            assert_eq!(error.path(), Some("v"));
+        }
+
+        let mut list = vec![TagType::Byte as u8];
+        list.extend_from_slice(&i32::MIN.to_be_bytes());
+        let error = read_err(&entry(TagType::List, &list), ReadLimits::default());
+        assert!(matches!(error.inner(), Error::NegativeLength(i32::MIN)));
+    }
+
+    #[test]
+    fn rejects_lengths_above_the_limit() {
+        let limits = ReadLimits {
+            max_len: 16,
+            ..ReadLimits::unlimited()
+        };
+        let mut payload = 17i32.to_be_bytes().to_vec();
+        payload.extend_from_slice(&[0; 17]);
+        let error = read_err(&entry(TagType::ByteArray, &payload), limits);
+        assert!(matches!(error.inner(), Error::LengthLimitExceeded(16)));
+
+        payload[3] = 16;
+        payload.pop();
+        assert!(read(&entry(TagType::ByteArray, &payload), limits).is_ok());
+    }
+
+    #[test]
+    fn oversized_lengths_fail_without_allocating() {
+        // Claims 2 GiB of data but ends right away
+        let error = read_err(
+            &entry(TagType::LongArray, &i32::MAX.to_be_bytes()),
+            ReadLimits::unlimited(),
+        );
+        assert!(matches!(error.inner(), Error::Io(_)));
+
+        let error = read_err(
+            &entry(TagType::LongArray, &i32::MAX.to_be_bytes()),
+            ReadLimits::network(),
+        );
+        assert!(
+            matches!(error.inner(), Error::ByteLimitExceeded(limit) if *limit == 2 * 1024 * 1024)
+        );
+    }
+
+    #[test]
+    fn limits_nesting_to_512() {
+        let (_, tag) = read(&nested_lists(512), ReadLimits::default()).unwrap();
+        assert!(tag.tag_type() == TagType::List);
+
+        let error = read_err(&nested_lists(513), ReadLimits::default());
+        assert!(matches!(error.inner(), Error::DepthLimitExceeded(512)));
+
+        let bytes = nested_lists(513);
+        let mut events = NbtEventReader::new(bytes.as_slice());
+        let error = events.find_map(Result::err).unwrap();
+        assert!(matches!(error.inner(), Error::DepthLimitExceeded(512)));
+    }
+
+    #[test]
+    fn charges_overhead_per_element() {
+        // 1000 Byte elements are 1000 bytes of payload, but 13 bytes each to
+        // the quota, like in Minecraft
+        let mut list = vec![TagType::Byte as u8];
+        list.extend_from_slice(&1000i32.to_be_bytes());
+        list.extend_from_slice(&[0; 1000]);
+        let bytes = entry(TagType::List, &list);
+
+        let limits = |max_bytes| ReadLimits {
+            max_bytes,
+            ..ReadLimits::unlimited()
+        };
+        let error = read_err(&bytes, limits(4 * bytes.len() as u64));
+        assert!(matches!(error.inner(), Error::ByteLimitExceeded(_)));
+        assert!(read(&bytes, limits(14_000)).is_ok());
+
+        // Skipping charges the same as reading
+        let mut decoder =
+            Decoder::new(bytes.as_slice()).with_limits(limits(4 * bytes.len() as u64));
+        decoder.start_root();
+        let tag_type = decoder.read_entry_type().unwrap();
+        decoder.read_string().unwrap();
+        let error = decoder.skip(tag_type).unwrap_err();
+        assert!(matches!(error, Error::ByteLimitExceeded(_)));
+    }
+
+    #[test]
+    fn failed_read_restores_depth() {
+        // A list holding a compound that ends in the middle of an Int tag
+        let list = [TagType::Compound as u8, 0, 0, 0, 1, TagType::Int as u8];
+        let bytes = entry(TagType::List, &list);
+
+        for skip in [false, true] {
+            let mut decoder = Decoder::new(bytes.as_slice());
+            let (tag_type, _) = decoder.read_tag_header().unwrap();
+            let result = match skip {
+                true => decoder.skip(tag_type),
+                false => decoder.read_headless_tag(tag_type).map(drop),
+            };
+            assert!(matches!(result.unwrap_err().inner(), Error::Io(_)));
+            assert_eq!(decoder.depth, 0);
+        }
+    }
+
+    #[test]
+    fn quota_is_reset_for_every_root() {
+        let bytes = entry(TagType::Int, &[0, 0, 0, 1]);
+        let mut twice = bytes.clone();
+        twice.extend_from_slice(&bytes);
+
+        let limits = ReadLimits {
+            max_bytes: 200,
+            ..ReadLimits::unlimited()
+        };
+        let mut decoder = Decoder::new(twice.as_slice()).with_limits(limits);
+        decoder.read_root().unwrap();
+        decoder.read_root().unwrap();
+    }
+
+    #[test]
+    fn reads_unnamed_roots() {
+        let bytes = [
+            TagType::Compound as u8,
+            TagType::Byte as u8,
+            0,
+            1,
+            b'a',
+            1,
+            0,
+        ];
+        let mut decoder = Decoder::new(&bytes[..]);
+        let tag = decoder.read_unnamed().unwrap();
+        assert!(tag == NbtTag::from_snbt("{a:1b}").unwrap());
+        assert!(decoder.into_inner().is_empty());
+
+        let bytes = [TagType::Int as u8, 0, 0, 0, 5];
+        assert!(Decoder::new(&bytes[..]).read_unnamed().unwrap() == NbtTag::Int(5));
+    }
+
+    #[test]
+    fn unnamed_roots_round_trip() {
+        for snbt in [
+            "{a:[{b:\"h\u{e9}\"}],c:[L;1L]}",
+            "[1s,2s]",
+            "\"text\"",
+            "1.5d",
+        ] {
+            let tag = NbtTag::from_snbt(snbt).unwrap();
+            let mut ser = crate::ser::Serializer::new(Vec::new());
+            ser.write_unnamed(&tag).unwrap();
+            let bytes = ser.into_inner().unwrap();
+
+            let mut decoder = Decoder::new(bytes.as_slice());
+            assert!(decoder.read_unnamed().unwrap() == tag, "{snbt}");
+            assert!(decoder.into_inner().is_empty(), "{snbt}");
+        }
     }
 }
@@ -0,0 +1,600 @@
+//! A pull parser reading NBT as a stream of events
+//!
+//! Unlike `from_reader`, nothing but the current value is kept in memory, and
+//! subtrees that are not needed can be skipped without allocating. Arrays are
+//! handed out in chunks of at most [`NbtEventReader::CHUNK_LEN`] elements.
+//!
+//! ```
+//! use nbt_rust::{de::{NbtEvent, NbtEventReader, Primitive}, NbtTag};
+//!
+//! # fn main() -> nbt_rust::error::Result<()> {
+//! let level = NbtTag::from_snbt(r#"{Data:{Player:{Inventory:[{id:"stone"}]},LevelName:"World"}}"#)?;
+//! let mut bytes = Vec::new();
+//! nbt_rust::ser::Serializer::new(&mut bytes).serialize_tag("", &level)?;
+//!
+//! let mut events = NbtEventReader::new(bytes.as_slice());
+//! let mut level_name = None;
+//! while let Some(event) = events.next_event()? {
+//!     match event {
+//!         NbtEvent::CompoundStart { name: Some(name) } if name == "Player" => {
+//!             events.skip_current()?;
+//!         }
+//!         NbtEvent::Value { name: Some(name), value: Primitive::String(value) }
+//!             if name == "LevelName" =>
+//!         {
+//!             level_name = Some(value);
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! assert_eq!(level_name.as_deref(), Some("World"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::Read;
+
+use super::{Decoder, ReadLimits};
+use crate::{
+    error::{Error, Result},
+    flavor::{Java, NbtFlavor},
+    mutf8::StringEncoding,
+    nbt_tag::TagType,
+    NbtTag,
+};
+
+/// A piece of the NBT stream read by an `NbtEventReader`
+///
+/// Names are `None` for list elements and for the root of flavors without a root name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtEvent {
+    /// A compound starts; its tags follow until the matching `End`
+    CompoundStart { name: Option<String> },
+    /// A list starts; `len` elements of `elem_type` follow, then an `End`
+    ListStart {
+        name: Option<String>,
+        elem_type: TagType,
+        len: usize,
+    },
+    /// A ByteArray, IntArray or LongArray starts; its elements follow
+    /// as `ArrayChunk`s, then an `End`
+    ArrayStart {
+        name: Option<String>,
+        array_type: TagType,
+        len: usize,
+    },
+    /// A tag that is neither a compound, a list nor an array
+    Value {
+        name: Option<String>,
+        value: Primitive,
+    },
+    /// The next elements of the array being read
+    ArrayChunk(ArrayChunk),
+    /// The innermost compound, list or array ends
+    End,
+}
+
+/// The payload of a tag that contains no other tags
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+}
+
+impl From<Primitive> for NbtTag {
+    fn from(value: Primitive) -> Self {
+        match value {
+            Primitive::Byte(v) => NbtTag::Byte(v),
+            Primitive::Short(v) => NbtTag::Short(v),
+            Primitive::Int(v) => NbtTag::Int(v),
+            Primitive::Long(v) => NbtTag::Long(v),
+            Primitive::Float(v) => NbtTag::Float(v),
+            Primitive::Double(v) => NbtTag::Double(v),
+            Primitive::String(v) => NbtTag::String(v),
+        }
+    }
+}
+
+/// Consecutive elements of an array tag
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayChunk {
+    Bytes(Vec<u8>),
+    Ints(Vec<i32>),
+    Longs(Vec<i64>),
+}
+
+/// A compound, list or array whose End event has not been read yet
+enum Frame {
+    Compound,
+    List {
+        elem_type: TagType,
+        remaining: usize,
+    },
+    Array {
+        array_type: TagType,
+        remaining: usize,
+    },
+}
+
+/// Reads a single root tag as a stream of `NbtEvent`s
+///
+/// Every `CompoundStart`, `ListStart` and `ArrayStart` is matched by an `End`,
+/// unless the container is skipped with `skip_current`. The reader also is an
+/// iterator over the events, which stops after the first error.
+pub struct NbtEventReader<R, F = Java> {
+    decoder: Decoder<R, F>,
+    stack: Vec<Frame>,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: Read> NbtEventReader<R> {
+    /// Creates a reader of uncompressed Java NBT with the default limits
+    pub fn new(reader: R) -> Self {
+        Self {
+            decoder: Decoder::new(reader),
+            stack: Vec::new(),
+            started: false,
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read, F: NbtFlavor> NbtEventReader<R, F> {
+    /// Maximum number of elements in an `ArrayChunk`
+    pub const CHUNK_LEN: usize = 4096;
+
+    /// Replaces the limits enforced while reading
+    pub fn with_limits(mut self, limits: ReadLimits) -> Self {
+        self.decoder = self.decoder.with_limits(limits);
+        self
+    }
+
+    /// Selects the flavor of NBT to read, Java by default.
+    /// This also selects the string encoding of that flavor
+    pub fn with_flavor<G: NbtFlavor>(self, flavor: G) -> NbtEventReader<R, G> {
+        NbtEventReader {
+            decoder: self.decoder.with_flavor(flavor),
+            stack: self.stack,
+            started: self.started,
+            finished: self.finished,
+        }
+    }
+
+    /// Replaces the encoding strings are read with, Modified UTF-8 by default
+    pub fn with_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.decoder = self.decoder.with_string_encoding(encoding);
+        self
+    }
+
+    /// Consumes the reader and returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner()
+    }
+
+    /// Number of compounds, lists and arrays that are open
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Reads the next event, or `None` once the root tag has been read completely
+    ///
+    /// Errors carry the byte offset at which they were detected.
+    pub fn next_event(&mut self) -> Result<Option<NbtEvent>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let event = self.read_event().map_err(|e| self.decoder.locate(e));
+        if !matches!(event, Ok(Some(_))) || (self.started && self.stack.is_empty()) {
+            self.finished = true;
+        }
+        event
+    }
+
+    /// Skips the rest of the innermost open compound, list or array, including its
+    /// `End`, without allocating. Right after a start event, this skips that container.
+    ///
+    /// Does nothing if no container is open.
+    pub fn skip_current(&mut self) -> Result<()> {
+        let Some(frame) = self.stack.pop() else {
+            return Ok(());
+        };
+        let decoder = &mut self.decoder;
+        let skipped = match frame {
+            Frame::Compound => decoder.skip_compound_entries().map(|()| decoder.exit()),
+            Frame::List {
+                elem_type,
+                remaining,
+            } => (0..remaining)
+                .try_for_each(|_| decoder.skip(elem_type))
+                .map(|()| decoder.exit()),
+            Frame::Array {
+                array_type,
+                remaining,
+            } => match array_type {
+                TagType::ByteArray => decoder.skip_bytes(remaining as u64),
+                TagType::IntArray => F::skip_int_array(decoder, remaining),
+                _ => F::skip_long_array(decoder, remaining),
+            },
+        };
+
+        if self.stack.is_empty() {
+            self.finished = true;
+        }
+        skipped.map_err(|e| {
+            self.finished = true;
+            self.decoder.locate(e)
+        })
+    }
+
+    fn read_event(&mut self) -> Result<Option<NbtEvent>> {
+        if !self.started {
+            self.started = true;
+            self.decoder.start_root();
+
+            let tag_type = self.decoder.read_entry_type()?;
+            if tag_type == TagType::End {
+                return Ok(None);
+            }
+            let name = match F::HAS_ROOT_NAME {
+                true => Some(self.decoder.read_string()?),
+                false => None,
+            };
+            return self.begin(tag_type, name).map(Some);
+        }
+
+        let Some(frame) = self.stack.last_mut() else {
+            return Ok(None);
+        };
+        let event = match frame {
+            Frame::Compound => {
+                let tag_type = self.decoder.read_entry_type()?;
+                if tag_type == TagType::End {
+                    self.stack.pop();
+                    self.decoder.exit();
+                    return Ok(Some(NbtEvent::End));
+                }
+                let name = self.decoder.read_string()?;
+                self.begin(tag_type, Some(name))?
+            }
+            Frame::List { remaining: 0, .. } => {
+                self.stack.pop();
+                self.decoder.exit();
+                NbtEvent::End
+            }
+            Frame::List {
+                elem_type,
+                remaining,
+            } => {
+                *remaining -= 1;
+                let elem_type = *elem_type;
+                self.begin(elem_type, None)?
+            }
+            Frame::Array { remaining: 0, .. } => {
+                self.stack.pop();
+                NbtEvent::End
+            }
+            Frame::Array {
+                array_type,
+                remaining,
+            } => {
+                let len = (*remaining).min(Self::CHUNK_LEN);
+                *remaining -= len;
+                let decoder = &mut self.decoder;
+                NbtEvent::ArrayChunk(match array_type {
+                    TagType::ByteArray => ArrayChunk::Bytes(decoder.read_bytes(len)?),
+                    TagType::IntArray => ArrayChunk::Ints(F::read_int_array(decoder, len)?),
+                    _ => ArrayChunk::Longs(F::read_long_array(decoder, len)?),
+                })
+            }
+        };
+        Ok(Some(event))
+    }
+
+    /// Reads what follows the header of a tag, opening a frame for containers
+    fn begin(&mut self, tag_type: TagType, name: Option<String>) -> Result<NbtEvent> {
+        let decoder = &mut self.decoder;
+        let value = match tag_type {
+            TagType::Byte => Primitive::Byte(decoder.read_i8()?),
+            TagType::Short => Primitive::Short(decoder.read_i16()?),
+            TagType::Int => Primitive::Int(decoder.read_i32()?),
+            TagType::Long => Primitive::Long(decoder.read_i64()?),
+            TagType::Float => Primitive::Float(decoder.read_f32()?),
+            TagType::Double => Primitive::Double(decoder.read_f64()?),
+            TagType::String => Primitive::String(decoder.read_string()?),
+            TagType::Compound => {
+                decoder.enter()?;
+                self.stack.push(Frame::Compound);
+                return Ok(NbtEvent::CompoundStart { name });
+            }
+            TagType::List => {
+                let (elem_type, len) = decoder.read_list_header()?;
+                decoder.enter()?;
+                self.stack.push(Frame::List {
+                    elem_type,
+                    remaining: len,
+                });
+                return Ok(NbtEvent::ListStart {
+                    name,
+                    elem_type,
+                    len,
+                });
+            }
+            TagType::ByteArray | TagType::IntArray | TagType::LongArray => {
+                let len = decoder.read_len()?;
+                self.stack.push(Frame::Array {
+                    array_type: tag_type,
+                    remaining: len,
+                });
+                return Ok(NbtEvent::ArrayStart {
+                    name,
+                    array_type: tag_type,
+                    len,
+                });
+            }
+            // End tags only close compounds, which the caller handles
+            TagType::End => return Err(Error::Unsupported("End tag outside a compound")),
+        };
+        Ok(NbtEvent::Value { name, value })
+    }
+}
+
+impl<R: Read, F: NbtFlavor> Iterator for NbtEventReader<R, F> {
+    type Item = Result<NbtEvent>;
+
+    fn next(&mut self) -> Option<Result<NbtEvent>> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flavor::JavaNetwork, ser::Serializer};
+
+    fn written(name: &str, snbt: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let tag = NbtTag::from_snbt(snbt).unwrap();
+        Serializer::new(&mut bytes)
+            .serialize_tag(name, &tag)
+            .unwrap();
+        bytes
+    }
+
+    fn read_all(bytes: &[u8]) -> Vec<NbtEvent> {
+        NbtEventReader::new(bytes).map(Result::unwrap).collect()
+    }
+
+    fn named(name: &str) -> Option<String> {
+        Some(name.to_string())
+    }
+
+    fn list(name: Option<String>, elem_type: TagType, len: usize) -> NbtEvent {
+        NbtEvent::ListStart {
+            name,
+            elem_type,
+            len,
+        }
+    }
+
+    fn array(name: Option<String>, array_type: TagType, len: usize) -> NbtEvent {
+        NbtEvent::ArrayStart {
+            name,
+            array_type,
+            len,
+        }
+    }
+
+    fn value(name: Option<String>, value: Primitive) -> NbtEvent {
+        NbtEvent::Value { name, value }
+    }
+
+    #[test]
+    fn nested_tree_events() {
+        // Compounds with a single entry, so the order is the same without preserve_order
+        let bytes = written("root", "{data:[{pos:[[1s],[]]},{ids:[I;1,-2]}]}");
+        let compound = |name| NbtEvent::CompoundStart { name };
+        use NbtEvent::End;
+        let expected = [
+            compound(named("root")),
+            list(named("data"), TagType::Compound, 2),
+            compound(None),
+            list(named("pos"), TagType::List, 2),
+            list(None, TagType::Short, 1),
+            value(None, Primitive::Short(1)),
+            End,
+            list(None, TagType::End, 0),
+            End,
+            End,
+            End,
+            compound(None),
+            array(named("ids"), TagType::IntArray, 2),
+            NbtEvent::ArrayChunk(ArrayChunk::Ints(vec![1, -2])),
+            End,
+            End,
+            End,
+            End,
+        ];
+        assert_eq!(read_all(&bytes), expected);
+    }
+
+    #[test]
+    fn reader_depth_follows_the_tree() {
+        let bytes = written("", "{a:[{b:[B;1b]}]}");
+        let mut reader = NbtEventReader::new(bytes.as_slice());
+        let mut depths = Vec::new();
+        while reader.next_event().unwrap().is_some() {
+            depths.push(reader.depth());
+        }
+        assert_eq!(depths, [1, 2, 3, 4, 4, 3, 2, 1, 0]);
+        assert!(reader.next_event().unwrap().is_none());
+    }
+
+    #[test]
+    fn arrays_are_split_into_chunks() {
+        const CHUNK_LEN: usize = NbtEventReader::<&[u8]>::CHUNK_LEN;
+        let len = CHUNK_LEN * 2 + 5;
+        let longs: Vec<i64> = (0..len as i64).collect();
+        let mut bytes = Vec::new();
+        Serializer::new(&mut bytes)
+            .serialize_tag("", &NbtTag::LongArray(longs.clone()))
+            .unwrap();
+
+        let events = read_all(&bytes);
+        assert_eq!(events[0], array(named(""), TagType::LongArray, len));
+        let chunks: Vec<&[i64]> = events[1..4]
+            .iter()
+            .map(|event| match event {
+                NbtEvent::ArrayChunk(ArrayChunk::Longs(chunk)) => chunk.as_slice(),
+                event => panic!("expected a chunk, got {event:?}"),
+            })
+            .collect();
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            [CHUNK_LEN, CHUNK_LEN, 5]
+        );
+        assert_eq!(chunks.concat(), longs);
+        assert_eq!(events[4..], [NbtEvent::End]);
+
+        let mut bytes = Vec::new();
+        Serializer::new(&mut bytes)
+            .serialize_tag("", &NbtTag::ByteArray(vec![7; CHUNK_LEN]))
+            .unwrap();
+        let events = read_all(&bytes);
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[1],
+            NbtEvent::ArrayChunk(ArrayChunk::Bytes(vec![7; CHUNK_LEN]))
+        );
+    }
+
+    /// Reads `events` events, skips the container the last of them opened, and returns the rest
+    fn skip_after(bytes: &[u8], events: usize) -> Vec<NbtEvent> {
+        let mut reader = NbtEventReader::new(bytes);
+        for _ in 0..events {
+            reader.next_event().unwrap().unwrap();
+        }
+        let depth = reader.depth();
+        reader.skip_current().unwrap();
+        assert_eq!(reader.depth(), depth - 1);
+        reader.map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn skip_current_skips_the_container_just_opened() {
+        use NbtEvent::End;
+
+        // root, v, first element
+        let compounds = written("", "{v:[{a:{b:[1,2]}},{c:3b}]}");
+        assert_eq!(
+            skip_after(&compounds, 3),
+            [
+                NbtEvent::CompoundStart { name: None },
+                value(named("c"), Primitive::Byte(3)),
+                End,
+                End,
+                End,
+            ]
+        );
+
+        let lists = written("", "{v:[[[1L],[2L]],[3s]]}");
+        assert_eq!(
+            skip_after(&lists, 3),
+            [
+                list(None, TagType::Short, 1),
+                value(None, Primitive::Short(3)),
+                End,
+                End,
+                End,
+            ]
+        );
+
+        let arrays = written("", "{v:[[I;1,2,3],[I;4]]}");
+        assert_eq!(
+            skip_after(&arrays, 3),
+            [
+                array(None, TagType::IntArray, 1),
+                NbtEvent::ArrayChunk(ArrayChunk::Ints(vec![4])),
+                End,
+                End,
+                End,
+            ]
+        );
+
+        // Skipping the root ends the stream
+        assert_eq!(skip_after(&compounds, 1), []);
+    }
+
+    #[test]
+    fn skip_current_skips_the_rest_of_a_container() {
+        let bytes = written("", "{v:[[B;1b,2b],[B;3b]]}");
+        let mut reader = NbtEventReader::new(bytes.as_slice());
+        for _ in 0..4 {
+            reader.next_event().unwrap();
+        }
+        // Inside the first ByteArray, after its chunk
+        reader.skip_current().unwrap();
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(array(None, TagType::ByteArray, 1))
+        );
+    }
+
+    #[test]
+    fn primitive_root() {
+        let bytes = written("n", "-5");
+        assert_eq!(read_all(&bytes), [value(named("n"), Primitive::Int(-5))]);
+
+        let bytes = written("s", "\"text\"");
+        let string = Primitive::String("text".to_string());
+        assert_eq!(read_all(&bytes), [value(named("s"), string)]);
+    }
+
+    #[test]
+    fn nameless_network_root() {
+        let tag = NbtTag::from_snbt("{a:1b}").unwrap();
+        let mut bytes = Vec::new();
+        let mut ser = Serializer::new(&mut bytes).with_flavor(JavaNetwork);
+        ser.serialize_tag("", &tag).unwrap();
+        drop(ser);
+
+        let reader = NbtEventReader::new(bytes.as_slice()).with_flavor(JavaNetwork);
+        let events: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(
+            events,
+            [
+                NbtEvent::CompoundStart { name: None },
+                value(named("a"), Primitive::Byte(1)),
+                NbtEvent::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let bytes = written("root", "{a:[{b:\"text\"}],c:[L;1L,2L],d:1.5d}");
+        for len in 0..bytes.len() {
+            let results: Vec<_> = NbtEventReader::new(&bytes[..len]).collect();
+            let error = match results.last() {
+                Some(Err(error)) => error,
+                _ => panic!("{len} bytes read without an error"),
+            };
+            assert!(
+                matches!(error.inner(), Error::Io(_)),
+                "{len} bytes: {error}"
+            );
+            assert!(error.offset().is_some());
+        }
+
+        // Skipping runs into the end of the input too
+        let mut reader = NbtEventReader::new(&bytes[..bytes.len() - 3]);
+        reader.next_event().unwrap();
+        assert!(reader.skip_current().is_err());
+        assert!(reader.next_event().unwrap().is_none());
+    }
+}
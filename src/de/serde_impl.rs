@@ -0,0 +1,661 @@
+use std::io::{Cursor, Read};
+
+use serde::de::{
+    self, value::StrDeserializer, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor,
+};
+
+use super::{Decoder, ReadLimits};
+use crate::{
+    compression::CompressedReader,
+    error::{Error, Result},
+    flavor::{Java, NbtFlavor},
+    mutf8::StringEncoding,
+    nbt_tag::TagType,
+    serde_with::{array_name, empty_list_name, forced_type, ArrayEntry, TAG},
+};
+
+/// Deserializes a value from the root tag of an NBT stream
+///
+/// Gzip and zlib compressed input is detected by its magic bytes
+/// and decompressed transparently. The name of the root tag is ignored.
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T> {
+    let mut de = Deserializer::new(CompressedReader::new(reader)?);
+    T::deserialize(&mut de)
+}
+
+/// Deserializes a value from the root tag of NBT bytes
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    from_reader(Cursor::new(bytes))
+}
+
+/// A serde deserializer reading binary NBT straight from the underlying reader,
+/// without building an intermediate `NbtTag` tree
+pub struct Deserializer<R, F = Java> {
+    decoder: Decoder<R, F>,
+    /// Names of the tags being read, used to report where errors happen.
+    /// The buffer is reused, so field names are matched without allocating
+    keys: Vec<u8>,
+}
+
+impl<R: Read> Deserializer<R> {
+    /// Creates a deserializer reading uncompressed Java NBT from the given reader
+    pub fn new(reader: R) -> Self {
+        Self {
+            decoder: Decoder::new(reader),
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read, F: NbtFlavor> Deserializer<R, F> {
+    /// Replaces the limits enforced while reading
+    pub fn with_limits(mut self, limits: ReadLimits) -> Self {
+        self.decoder = self.decoder.with_limits(limits);
+        self
+    }
+
+    /// Selects the flavor of NBT to read, Java by default.
+    /// This also selects the string encoding of that flavor
+    pub fn with_flavor<G: NbtFlavor>(self, flavor: G) -> Deserializer<R, G> {
+        Deserializer {
+            decoder: self.decoder.with_flavor(flavor),
+            keys: self.keys,
+        }
+    }
+
+    /// Replaces the encoding strings are read with, Modified UTF-8 by default
+    pub fn with_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.decoder = self.decoder.with_string_encoding(encoding);
+        self
+    }
+
+    /// Consumes the deserializer and returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner()
+    }
+
+    /// Reads the header of the root tag, discarding its name if it has one
+    fn read_root_header(&mut self) -> Result<TagType> {
+        let tag_type = self.decoder.read_entry_type()?;
+        if tag_type != TagType::End && F::HAS_ROOT_NAME {
+            let start = self.push_key()?;
+            self.keys.truncate(start);
+        }
+        Ok(tag_type)
+    }
+
+    /// Reads a tag name onto the key stack and returns where it starts
+    fn push_key(&mut self) -> Result<usize> {
+        let len = self.decoder.read_string_len()?;
+        let start = self.keys.len();
+        self.keys.resize(start + len, 0);
+        self.decoder.read_exact(&mut self.keys[start..])?;
+
+        // Names that are not plain UTF-8 are converted in place
+        if std::str::from_utf8(&self.keys[start..]).is_err() {
+            let raw = self.keys.split_off(start);
+            let key = self.decoder.encoding.decode(raw)?;
+            self.keys.extend_from_slice(key.as_bytes());
+        }
+        Ok(start)
+    }
+
+    /// The tag name starting at `start` on the key stack
+    fn key(&self, start: usize) -> &str {
+        std::str::from_utf8(&self.keys[start..]).unwrap_or_default()
+    }
+
+    /// Pops a tag name off the key stack, recording it in the path of an error
+    fn pop_key<T>(&mut self, start: usize, result: Result<T>) -> Result<T> {
+        let result = result.map_err(|e| e.in_key(self.key(start)));
+        self.keys.truncate(start);
+        result
+    }
+}
+
+impl<'de, R: Read, F: NbtFlavor> de::Deserializer<'de> for &mut Deserializer<R, F> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let result = self.read_root_header().and_then(|tag_type| {
+            ValueDeserializer {
+                de: &mut *self,
+                tag_type,
+            }
+            .deserialize_any(visitor)
+        });
+        result.map_err(|e| self.decoder.locate(e))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let result = self.read_root_header().and_then(|tag_type| {
+            ValueDeserializer {
+                de: &mut *self,
+                tag_type,
+            }
+            .deserialize_option(visitor)
+        });
+        result.map_err(|e| self.decoder.locate(e))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        let result = self.read_root_header().and_then(|tag_type| {
+            ValueDeserializer {
+                de: &mut *self,
+                tag_type,
+            }
+            .deserialize_newtype_struct(name, visitor)
+        });
+        result.map_err(|e| self.decoder.locate(e))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let result = self.read_root_header().and_then(|tag_type| {
+            ValueDeserializer {
+                de: &mut *self,
+                tag_type,
+            }
+            .deserialize_enum(name, variants, visitor)
+        });
+        result.map_err(|e| self.decoder.locate(e))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Deserializes the payload of a tag whose type has already been read
+struct ValueDeserializer<'a, R, F> {
+    de: &'a mut Deserializer<R, F>,
+    tag_type: TagType,
+}
+
+impl<R: Read, F: NbtFlavor> ValueDeserializer<'_, R, F> {
+    /// Reads the payload if this is a Byte tag
+    fn byte(&mut self) -> Result<Option<i8>> {
+        match self.tag_type {
+            TagType::Byte => Ok(Some(self.de.decoder.read_i8()?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Visits the elements of a List tag whose header has been read
+    fn list<'de, V: Visitor<'de>>(
+        self,
+        elem_type: TagType,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.de.decoder.enter()?;
+        let value = visitor.visit_seq(SeqAccess::new(&mut *self.de, elem_type, len));
+        self.de.decoder.exit();
+        value
+    }
+}
+
+impl<'de, R: Read, F: NbtFlavor> de::Deserializer<'de> for ValueDeserializer<'_, R, F> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let decoder = &mut self.de.decoder;
+        match self.tag_type {
+            TagType::End => visitor.visit_unit(),
+            TagType::Byte => visitor.visit_i8(decoder.read_i8()?),
+            TagType::Short => visitor.visit_i16(decoder.read_i16()?),
+            TagType::Int => visitor.visit_i32(decoder.read_i32()?),
+            TagType::Long => visitor.visit_i64(decoder.read_i64()?),
+            TagType::Float => visitor.visit_f32(decoder.read_f32()?),
+            TagType::Double => visitor.visit_f64(decoder.read_f64()?),
+            TagType::String => visitor.visit_string(decoder.read_string()?),
+            TagType::ByteArray => {
+                let len = decoder.read_len()?;
+                visitor.visit_seq(SeqAccess::new(self.de, TagType::Byte, len))
+            }
+            TagType::IntArray => {
+                let len = decoder.read_len()?;
+                visitor.visit_seq(SeqAccess::new(self.de, TagType::Int, len))
+            }
+            TagType::LongArray => {
+                let len = decoder.read_len()?;
+                visitor.visit_seq(SeqAccess::new(self.de, TagType::Long, len))
+            }
+            TagType::List => {
+                let (elem_type, len) = decoder.read_list_header()?;
+                self.list(elem_type, len, visitor)
+            }
+            TagType::Compound => {
+                decoder.enter()?;
+                let value = visitor.visit_map(MapAccess {
+                    de: &mut *self.de,
+                    pending: None,
+                });
+                self.de.decoder.exit();
+                value
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        match self.byte()? {
+            Some(v) => visitor.visit_bool(v != 0),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    // Unsigned integers are written as their two's complement bit pattern,
+    // so they are read back the same way instead of being range checked
+    fn deserialize_u8<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        match self.byte()? {
+            Some(v) => visitor.visit_u8(v as u8),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag_type {
+            TagType::Short => visitor.visit_u16(self.de.decoder.read_i16()? as u16),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag_type {
+            TagType::Int => visitor.visit_u32(self.de.decoder.read_i32()? as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag_type {
+            TagType::Long => visitor.visit_u64(self.de.decoder.read_i64()? as u64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag_type {
+            TagType::ByteArray => {
+                let len = self.de.decoder.read_len()?;
+                visitor.visit_byte_buf(self.de.decoder.read_bytes(len)?)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag_type {
+            TagType::End => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if name == TAG {
+            if self.tag_type == TagType::List {
+                let (elem_type, len) = self.de.decoder.read_list_header()?;
+                if len == 0 && elem_type != TagType::End {
+                    let name = empty_list_name(elem_type);
+                    return visitor.visit_map(ArrayEntry::new(name, ().into_deserializer()));
+                }
+                return self.list(elem_type, len, visitor);
+            }
+            return match array_name(self.tag_type) {
+                Some(array) => visitor.visit_map(ArrayEntry::new(array, self)),
+                None => self.deserialize_any(visitor),
+            };
+        }
+        match forced_type(name) {
+            Some(expected) if expected != self.tag_type => Err(Error::TypeMismatch {
+                expected,
+                actual: self.tag_type,
+            }),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.tag_type {
+            // Unit variants are stored as their name
+            TagType::String => {
+                let variant = self.de.decoder.read_string()?;
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            // Other variants are a compound with a single tag named after the variant
+            TagType::Compound => {
+                self.de.decoder.enter()?;
+                let value = visitor.visit_enum(EnumAccess { de: &mut *self.de });
+                self.de.decoder.exit();
+                value
+            }
+            _ => Err(de::Error::custom(
+                "expected a String or a Compound tag for an enum",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.de.decoder.skip(self.tag_type)?;
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Reads the elements of a List tag or of an array tag
+struct SeqAccess<'a, R, F> {
+    de: &'a mut Deserializer<R, F>,
+    elem_type: TagType,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, R, F> SeqAccess<'a, R, F> {
+    fn new(de: &'a mut Deserializer<R, F>, elem_type: TagType, len: usize) -> Self {
+        Self {
+            de,
+            elem_type,
+            index: 0,
+            len,
+        }
+    }
+}
+
+impl<'de, R: Read, F: NbtFlavor> de::SeqAccess<'de> for SeqAccess<'_, R, F> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+
+        seed.deserialize(ValueDeserializer {
+            de: &mut *self.de,
+            tag_type: self.elem_type,
+        })
+        .map(Some)
+        .map_err(|e| e.in_index(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+/// Reads the named tags of a Compound tag until its End tag
+struct MapAccess<'a, R, F> {
+    de: &'a mut Deserializer<R, F>,
+    /// Type of the tag whose name was just read as a key, and where that name starts
+    pending: Option<(TagType, usize)>,
+}
+
+impl<'de, R: Read, F: NbtFlavor> de::MapAccess<'de> for MapAccess<'_, R, F> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let tag_type = self.de.decoder.read_entry_type()?;
+        if tag_type == TagType::End {
+            return Ok(None);
+        }
+
+        let start = self.de.push_key()?;
+        let key = seed.deserialize(StrDeserializer::<Error>::new(self.de.key(start)));
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => return self.de.pop_key(start, Err(e)),
+        };
+        self.pending = Some((tag_type, start));
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (tag_type, start) = self
+            .pending
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("next_value called before next_key"))?;
+
+        let value = seed.deserialize(ValueDeserializer {
+            de: &mut *self.de,
+            tag_type,
+        });
+        self.de.pop_key(start, value)
+    }
+}
+
+/// Reads an enum stored as a compound with a single tag named after the variant
+struct EnumAccess<'a, R, F> {
+    de: &'a mut Deserializer<R, F>,
+}
+
+impl<'a, 'de, R: Read, F: NbtFlavor> de::EnumAccess<'de> for EnumAccess<'a, R, F> {
+    type Error = Error;
+    type Variant = VariantAccess<'a, R, F>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let tag_type = self.de.decoder.read_entry_type()?;
+        if tag_type == TagType::End {
+            return Err(de::Error::custom(
+                "expected an enum variant, found an empty compound",
+            ));
+        }
+
+        let start = self.de.push_key()?;
+        let variant = seed.deserialize(StrDeserializer::<Error>::new(self.de.key(start)));
+        let variant = match variant {
+            Ok(variant) => variant,
+            Err(e) => return self.de.pop_key(start, Err(e)),
+        };
+        Ok((
+            variant,
+            VariantAccess {
+                de: self.de,
+                tag_type,
+                start,
+            },
+        ))
+    }
+}
+
+struct VariantAccess<'a, R, F> {
+    de: &'a mut Deserializer<R, F>,
+    tag_type: TagType,
+    /// Where the variant name starts on the key stack
+    start: usize,
+}
+
+impl<R: Read, F: NbtFlavor> VariantAccess<'_, R, F> {
+    fn value(&mut self) -> ValueDeserializer<'_, R, F> {
+        ValueDeserializer {
+            de: &mut *self.de,
+            tag_type: self.tag_type,
+        }
+    }
+
+    /// Pops the variant name off the key stack and reads the End tag
+    /// closing the compound that wraps the variant
+    fn end<T>(self, value: Result<T>) -> Result<T> {
+        let value = self.de.pop_key(self.start, value)?;
+        match self.de.decoder.read_tag_type()? {
+            TagType::End => Ok(value),
+            _ => Err(de::Error::custom(
+                "expected a single tag in an enum compound",
+            )),
+        }
+    }
+}
+
+impl<'de, R: Read, F: NbtFlavor> de::VariantAccess<'de> for VariantAccess<'_, R, F> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        let skipped = self.de.decoder.skip(self.tag_type);
+        self.end(skipped)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(mut self, seed: T) -> Result<T::Value> {
+        let value = seed.deserialize(self.value());
+        self.end(value)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(mut self, _len: usize, visitor: V) -> Result<V::Value> {
+        let value = de::Deserializer::deserialize_seq(self.value(), visitor);
+        self.end(value)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        mut self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let value = de::Deserializer::deserialize_map(self.value(), visitor);
+        self.end(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        compression::Compression,
+        ser::{to_bytes, Serializer},
+        NbtTag,
+    };
+
+    /// Writes SNBT as binary NBT with the tag API, independently of the serde serializer
+    fn bytes(snbt: &str, compression: Compression) -> Vec<u8> {
+        let mut ser = Serializer::with_compression(Vec::new(), compression);
+        ser.serialize_tag("root", &NbtTag::from_snbt(snbt).unwrap())
+            .unwrap();
+        ser.into_inner().unwrap()
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Mode {
+        Creative,
+        Adventure { strict: bool },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Slot {
+        #[serde(rename = "Slot")]
+        slot: i8,
+        id: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Player {
+        name: String,
+        level: i32,
+        #[serde(rename = "XpP")]
+        xp: f32,
+        flying: bool,
+        color: u8,
+        inventory: Vec<Slot>,
+        uuid: Vec<i32>,
+        modes: Vec<Mode>,
+        spawn: Option<i64>,
+    }
+
+    const PLAYER: &str = r#"{
+        name: "Alex", level: 30, XpP: 0.5f, flying: 1b, color: -1b, ignored: {deep: [[1, 2]]},
+        inventory: [{Slot: 2b, id: "stone", Count: 1b}], uuid: [I; 1, 2, 3, 4], modes: [{Adventure: {strict: 0b}}]
+    }"#;
+
+    #[test]
+    fn reads_structs_written_as_tags() {
+        for compression in [Compression::None, Compression::Gzip(6)] {
+            let player: Player = from_bytes(&bytes(PLAYER, compression)).unwrap();
+            assert_eq!(
+                player,
+                Player {
+                    name: "Alex".into(),
+                    level: 30,
+                    xp: 0.5,
+                    flying: true,
+                    color: 255,
+                    inventory: vec![Slot {
+                        slot: 2,
+                        id: "stone".into(),
+                    }],
+                    uuid: vec![1, 2, 3, 4],
+                    modes: vec![Mode::Adventure { strict: false }],
+                    spawn: None,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_through_tags() {
+        let tag = NbtTag::from_snbt(PLAYER).unwrap();
+        let read: NbtTag = from_bytes(&bytes(PLAYER, Compression::Zlib(6))).unwrap();
+        assert!(read == tag);
+        assert!(from_bytes::<NbtTag>(&to_bytes("", &tag).unwrap()).unwrap() == tag);
+        assert!(read["uuid"] == NbtTag::IntArray(vec![1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn unit_variants_are_read_from_strings() {
+        let modes: Vec<Mode> = from_bytes(&bytes(r#"["Creative"]"#, Compression::None)).unwrap();
+        assert_eq!(modes, [Mode::Creative]);
+    }
+
+    #[test]
+    fn errors_know_where_they_happened() {
+        let snbt = PLAYER.replace("level: 30", r#"level: "thirty""#);
+        let error = from_bytes::<Player>(&bytes(&snbt, Compression::None)).unwrap_err();
+        assert!(matches!(error.inner(), Error::Message(_)));
+        assert_eq!(error.path(), Some("level"));
+        assert!(error.offset().is_some());
+
+        let snbt = PLAYER.replace("strict: 0b", "strict: 0b}, Creative: {");
+        let error = from_bytes::<Player>(&bytes(&snbt, Compression::None)).unwrap_err();
+        assert!(error.to_string().contains("single tag"), "{error}");
+        assert_eq!(error.path(), Some("modes[0]"));
+    }
+
+    #[test]
+    fn missing_fields_are_reported() {
+        let error =
+            from_bytes::<Player>(&bytes("{name: \"Alex\"}", Compression::None)).unwrap_err();
+        assert!(error.to_string().contains("missing field"), "{error}");
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let bytes = bytes(PLAYER, Compression::None);
+        let error = from_bytes::<Player>(&bytes[..bytes.len() - 3]).unwrap_err();
+        assert!(matches!(error.inner(), Error::Io(_)));
+    }
+}
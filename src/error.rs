@@ -1,12 +1,155 @@
 use std::{error, fmt, io};
 
+use crate::{nbt_tag::TagType, snbt::SnbtError};
+
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
     Utf8(std::string::FromUtf8Error),
+    /// Invalid Modified UTF-8 at the given index of the string
+    Mutf8(usize),
+    StringTooLong(usize),
+    /// The payload length in a Bedrock `level.dat` header does not match the file
+    HeaderLengthMismatch {
+        header: u32,
+        actual: usize,
+    },
     UnknownTagType(u8),
-    EmptySequence,
     ElementTypesDiffer,
+    UnknownLength,
+    NegativeLength(i32),
+    VarIntTooLong,
+    LengthLimitExceeded(usize),
+    DepthLimitExceeded(usize),
+    ByteLimitExceeded(u64),
+    KeyMustBeString,
+    /// A compound has no tag with the given name
+    MissingKey(String),
+    /// A tag is not of the type it was accessed as
+    TypeMismatch {
+        expected: TagType,
+        actual: TagType,
+    },
+    /// An index outside a list of the given length
+    IndexOutOfBounds {
+        index: i32,
+        len: usize,
+    },
+    /// A list or array does not have the number of elements a fixed-size type needs
+    LengthMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    Unsupported(&'static str),
+    Message(String),
+    Snbt(SnbtError),
+    /// An error annotated with where it happened
+    Located {
+        error: Box<Error>,
+        /// Number of bytes read before the error was detected, when decoding
+        offset: Option<u64>,
+        /// Path to the offending tag, such as `Level.Sections[3].BlockStates`
+        path: String,
+    },
+}
+
+impl Error {
+    /// The error without the location it happened at
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Byte offset into the (decompressed) input at which a decode error was detected
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Error::Located { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// Path to the tag being read or written when the error happened
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::Located { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Records that the error happened inside the tag with the given name
+    pub(crate) fn in_key(self, key: &str) -> Error {
+        self.prepend_path(|path| {
+            if is_plain_key(key) {
+                path.push_str(key);
+            } else {
+                path.push_str(&format!("{key:?}"));
+            }
+        })
+    }
+
+    /// Records that the error happened inside the list element with the given index
+    pub(crate) fn in_index(self, index: usize) -> Error {
+        self.prepend_path(|path| path.push_str(&format!("[{index}]")))
+    }
+
+    /// Records the offset at which the error was detected, unless one is already known
+    pub(crate) fn at_offset(self, at: u64) -> Error {
+        match self {
+            Error::Located {
+                error,
+                offset,
+                path,
+            } => Error::Located {
+                error,
+                offset: offset.or(Some(at)),
+                path,
+            },
+            error => Error::Located {
+                error: Box::new(error),
+                offset: Some(at),
+                path: String::new(),
+            },
+        }
+    }
+
+    /// Records an NBT path the error happened at
+    pub(crate) fn in_path(self, path: &str) -> Error {
+        self.prepend_path(|p| p.push_str(path))
+    }
+
+    fn prepend_path(self, segment: impl FnOnce(&mut String)) -> Error {
+        let (error, offset, rest) = match self {
+            Error::Located {
+                error,
+                offset,
+                path,
+            } => (error, offset, path),
+            error => (Box::new(error), None, String::new()),
+        };
+
+        let mut path = String::new();
+        segment(&mut path);
+        if !rest.is_empty() && !rest.starts_with('[') {
+            path.push('.');
+        }
+        path.push_str(&rest);
+
+        Error::Located {
+            error,
+            offset,
+            path,
+        }
+    }
+}
+
+/// Whether a key can appear in a path without quotes
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
 }
 
 impl From<io::Error> for Error {
@@ -21,18 +164,85 @@ impl From<std::string::FromUtf8Error> for Error {
     }
 }
 
+impl From<SnbtError> for Error {
+    fn from(error: SnbtError) -> Error {
+        Error::Snbt(error)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::Io(error) => write!(f, "IO error: {error}"),
             Error::Utf8(error) => write!(f, "UTF-8 error: {error}"),
+            Error::Mutf8(index) => write!(f, "Invalid Modified UTF-8 at index {index}"),
+            Error::StringTooLong(len) => {
+                write!(f, "String of {len} bytes is too long for its length prefix")
+            }
             Error::UnknownTagType(byte) => write!(f, "Unknown tag type: {byte}"),
-            Error::EmptySequence => write!(f, "Empty sequence"),
             Error::ElementTypesDiffer => write!(f, "Element types differ"),
+            Error::UnknownLength => write!(f, "Sequence length must be known in advance"),
+            Error::NegativeLength(len) => write!(f, "Negative length: {len}"),
+            Error::VarIntTooLong => write!(f, "VarInt is too long"),
+            Error::HeaderLengthMismatch { header, actual } => write!(
+                f,
+                "Header declares {header} bytes of NBT, but {actual} were found"
+            ),
+            Error::LengthLimitExceeded(limit) => {
+                write!(f, "List or array longer than the limit of {limit} elements")
+            }
+            Error::DepthLimitExceeded(limit) => {
+                write!(f, "Tags nested deeper than the limit of {limit}")
+            }
+            Error::ByteLimitExceeded(limit) => {
+                write!(f, "Input larger than the limit of {limit} bytes")
+            }
+            Error::KeyMustBeString => write!(f, "Map key must be a string"),
+            Error::MissingKey(key) => write!(f, "Missing tag {key:?}"),
+            Error::TypeMismatch { expected, actual } => {
+                write!(f, "Expected a tag of type {expected:?}, found {actual:?}")
+            }
+            Error::IndexOutOfBounds { index, len } => {
+                write!(f, "Index {index} is out of bounds for a list of {len} tags")
+            }
+            Error::LengthMismatch { expected, actual } => {
+                write!(f, "Expected {expected} elements, found {actual}")
+            }
+            Error::Unsupported(what) => write!(f, "Unsupported type: {what}"),
+            Error::Message(msg) => write!(f, "{msg}"),
+            Error::Snbt(error) => write!(f, "SNBT error: {error}"),
+            Error::Located {
+                error,
+                offset,
+                path,
+            } => {
+                write!(f, "{error}")?;
+                if !path.is_empty() {
+                    write!(f, " at {path}")?;
+                }
+                match offset {
+                    Some(offset) => write!(f, " (byte {offset})"),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
 
 impl error::Error for Error {}
 
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,445 @@
+use std::io;
+
+use crate::{
+    error::{Error, Result},
+    mutf8::StringEncoding,
+};
+
+/// The binary layout of NBT, which differs between Minecraft editions
+///
+/// The decoder and the serializer walk the tag tree the same way for every flavor
+/// and leave the encoding of numbers and length prefixes to it. Bytes, tag types and
+/// the contents of byte arrays and strings are laid out identically in all flavors,
+/// and the lengths of lists and arrays are ints.
+///
+/// The provided methods read and write fixed-width numbers in the byte order
+/// selected by `LITTLE_ENDIAN`, so a flavor only overrides what it does differently.
+pub trait NbtFlavor {
+    /// The encoding strings use in this flavor
+    const STRING_ENCODING: StringEncoding;
+
+    /// Whether the root tag is followed by a name
+    const HAS_ROOT_NAME: bool = true;
+
+    /// Whether fixed-width numbers are little-endian rather than big-endian
+    const LITTLE_ENDIAN: bool = false;
+
+    fn read_short<R: ReadBytes>(r: &mut R) -> Result<i16> {
+        let bytes = r.read_array()?;
+        Ok(match Self::LITTLE_ENDIAN {
+            true => i16::from_le_bytes(bytes),
+            false => i16::from_be_bytes(bytes),
+        })
+    }
+
+    fn read_int<R: ReadBytes>(r: &mut R) -> Result<i32> {
+        let bytes = r.read_array()?;
+        Ok(match Self::LITTLE_ENDIAN {
+            true => i32::from_le_bytes(bytes),
+            false => i32::from_be_bytes(bytes),
+        })
+    }
+
+    fn read_long<R: ReadBytes>(r: &mut R) -> Result<i64> {
+        let bytes = r.read_array()?;
+        Ok(match Self::LITTLE_ENDIAN {
+            true => i64::from_le_bytes(bytes),
+            false => i64::from_be_bytes(bytes),
+        })
+    }
+
+    fn read_float<R: ReadBytes>(r: &mut R) -> Result<f32> {
+        let bytes = r.read_array()?;
+        Ok(match Self::LITTLE_ENDIAN {
+            true => f32::from_le_bytes(bytes),
+            false => f32::from_be_bytes(bytes),
+        })
+    }
+
+    fn read_double<R: ReadBytes>(r: &mut R) -> Result<f64> {
+        let bytes = r.read_array()?;
+        Ok(match Self::LITTLE_ENDIAN {
+            true => f64::from_le_bytes(bytes),
+            false => f64::from_be_bytes(bytes),
+        })
+    }
+
+    /// Reads the length prefix of a string
+    fn read_string_len<R: ReadBytes>(r: &mut R) -> Result<usize> {
+        Ok(Self::read_short(r)? as u16 as usize)
+    }
+
+    /// Reads the elements of an IntArray whose length was already read
+    fn read_int_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<Vec<i32>> {
+        let bytes = r.read_vec(len * 4)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|c| {
+                let int = [c[0], c[1], c[2], c[3]];
+                match Self::LITTLE_ENDIAN {
+                    true => i32::from_le_bytes(int),
+                    false => i32::from_be_bytes(int),
+                }
+            })
+            .collect())
+    }
+
+    /// Reads the elements of a LongArray whose length was already read
+    fn read_long_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<Vec<i64>> {
+        let bytes = r.read_vec(len * 8)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|c| {
+                let long = [c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]];
+                match Self::LITTLE_ENDIAN {
+                    true => i64::from_le_bytes(long),
+                    false => i64::from_be_bytes(long),
+                }
+            })
+            .collect())
+    }
+
+    /// Discards the elements of an IntArray whose length was already read
+    fn skip_int_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<()> {
+        r.skip(len as u64 * 4)
+    }
+
+    /// Discards the elements of a LongArray whose length was already read
+    fn skip_long_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<()> {
+        r.skip(len as u64 * 8)
+    }
+
+    fn write_short<W: io::Write + ?Sized>(w: &mut W, v: i16) -> io::Result<()> {
+        w.write_all(&match Self::LITTLE_ENDIAN {
+            true => v.to_le_bytes(),
+            false => v.to_be_bytes(),
+        })
+    }
+
+    fn write_int<W: io::Write + ?Sized>(w: &mut W, v: i32) -> io::Result<()> {
+        w.write_all(&match Self::LITTLE_ENDIAN {
+            true => v.to_le_bytes(),
+            false => v.to_be_bytes(),
+        })
+    }
+
+    fn write_long<W: io::Write + ?Sized>(w: &mut W, v: i64) -> io::Result<()> {
+        w.write_all(&match Self::LITTLE_ENDIAN {
+            true => v.to_le_bytes(),
+            false => v.to_be_bytes(),
+        })
+    }
+
+    fn write_float<W: io::Write + ?Sized>(w: &mut W, v: f32) -> io::Result<()> {
+        w.write_all(&match Self::LITTLE_ENDIAN {
+            true => v.to_le_bytes(),
+            false => v.to_be_bytes(),
+        })
+    }
+
+    fn write_double<W: io::Write + ?Sized>(w: &mut W, v: f64) -> io::Result<()> {
+        w.write_all(&match Self::LITTLE_ENDIAN {
+            true => v.to_le_bytes(),
+            false => v.to_be_bytes(),
+        })
+    }
+
+    /// Writes the length prefix of a string, failing with `Error::StringTooLong`
+    /// if the prefix cannot hold it. By default, that is an unsigned short
+    fn write_string_len<W: io::Write + ?Sized>(w: &mut W, len: usize) -> Result<()> {
+        let len = u16::try_from(len).map_err(|_| Error::StringTooLong(len))?;
+        Self::write_short(w, len as i16)?;
+        Ok(())
+    }
+}
+
+/// Input an `NbtFlavor` reads from, which keeps track of the limits of the decoder
+pub trait ReadBytes {
+    /// Fills the buffer, failing if the input ends first
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()>;
+
+    /// Reads `len` bytes into a vector that only grows as data actually arrives,
+    /// so a bogus length cannot trigger a huge allocation up front
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>>;
+
+    /// Discards `len` bytes
+    fn skip(&mut self, len: u64) -> Result<()>;
+
+    /// Reads exactly `N` bytes
+    #[inline]
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.fill(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Java Edition: big-endian numbers and Modified UTF-8 strings
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Java;
+
+impl NbtFlavor for Java {
+    const STRING_ENCODING: StringEncoding = StringEncoding::Mutf8;
+}
+
+/// Java Edition network protocol since 1.20.2 (protocol 764):
+/// like `Java`, except that the root tag has no name
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JavaNetwork;
+
+impl NbtFlavor for JavaNetwork {
+    const STRING_ENCODING: StringEncoding = StringEncoding::Mutf8;
+    const HAS_ROOT_NAME: bool = false;
+}
+
+/// Bedrock Edition files (`level.dat`, `.mcstructure`, LevelDB values):
+/// little-endian numbers and standard UTF-8 strings
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bedrock;
+
+impl NbtFlavor for Bedrock {
+    const STRING_ENCODING: StringEncoding = StringEncoding::Utf8;
+    const LITTLE_ENDIAN: bool = true;
+}
+
+/// Bedrock Edition network protocol: like `Bedrock`, except that ints and
+/// lengths are zig-zag VarInts, longs are zig-zag VarLongs and string lengths
+/// are unsigned VarInts, so strings are not limited to 65535 bytes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BedrockNetwork;
+
+impl NbtFlavor for BedrockNetwork {
+    const STRING_ENCODING: StringEncoding = StringEncoding::Utf8;
+    const LITTLE_ENDIAN: bool = true;
+
+    fn read_int<R: ReadBytes>(r: &mut R) -> Result<i32> {
+        let zigzag = read_var(r, 5)? as u32;
+        Ok((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))
+    }
+
+    fn read_long<R: ReadBytes>(r: &mut R) -> Result<i64> {
+        let zigzag = read_var(r, 10)?;
+        Ok((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+    }
+
+    fn read_string_len<R: ReadBytes>(r: &mut R) -> Result<usize> {
+        Ok(read_var(r, 5)? as u32 as usize)
+    }
+
+    fn read_int_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<Vec<i32>> {
+        let mut ints = Vec::new();
+        for _ in 0..len {
+            ints.push(Self::read_int(r)?);
+        }
+        Ok(ints)
+    }
+
+    fn read_long_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<Vec<i64>> {
+        let mut longs = Vec::new();
+        for _ in 0..len {
+            longs.push(Self::read_long(r)?);
+        }
+        Ok(longs)
+    }
+
+    fn skip_int_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<()> {
+        for _ in 0..len {
+            Self::read_int(r)?;
+        }
+        Ok(())
+    }
+
+    fn skip_long_array<R: ReadBytes>(r: &mut R, len: usize) -> Result<()> {
+        for _ in 0..len {
+            Self::read_long(r)?;
+        }
+        Ok(())
+    }
+
+    fn write_int<W: io::Write + ?Sized>(w: &mut W, v: i32) -> io::Result<()> {
+        write_var(w, ((v << 1) ^ (v >> 31)) as u32 as u64)
+    }
+
+    fn write_long<W: io::Write + ?Sized>(w: &mut W, v: i64) -> io::Result<()> {
+        write_var(w, ((v << 1) ^ (v >> 63)) as u64)
+    }
+
+    fn write_string_len<W: io::Write + ?Sized>(w: &mut W, len: usize) -> Result<()> {
+        let len = u32::try_from(len).map_err(|_| Error::StringTooLong(len))?;
+        write_var(w, len as u64)?;
+        Ok(())
+    }
+}
+
+/// Reads an unsigned LEB128 VarInt spanning at most `max_len` bytes
+fn read_var<R: ReadBytes>(r: &mut R, max_len: u32) -> Result<u64> {
+    let mut value = 0;
+    for i in 0..max_len {
+        let [byte] = r.read_array()?;
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::VarIntTooLong)
+}
+
+/// Writes an unsigned LEB128 VarInt
+fn write_var<W: io::Write + ?Sized>(w: &mut W, mut v: u64) -> io::Result<()> {
+    let mut bytes = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (v & 0x7F) as u8;
+        v >>= 7;
+        if v == 0 {
+            bytes[len] = byte;
+            return w.write_all(&bytes[..len + 1]);
+        }
+        bytes[len] = byte | 0x80;
+        len += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        de::{from_bytes, from_bytes_flavored},
+        nbt_tag::{NbtCompound, NbtList, TagType},
+        ser::Serializer,
+        NbtTag,
+    };
+
+    /// Writes a root compound named "" holding a single entry named "v"
+    fn write_entry<F: NbtFlavor>(flavor: F, tag: NbtTag) -> Vec<u8> {
+        let mut compound = NbtCompound::new();
+        compound.insert("v".to_string(), tag);
+        let mut ser = Serializer::new(Vec::new()).with_flavor(flavor);
+        ser.serialize_tag("", &NbtTag::Compound(compound)).unwrap();
+        ser.into_inner().unwrap()
+    }
+
+    /// The bytes of such a root compound, with little-endian name lengths
+    fn le_entry(tag_type: TagType, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![TagType::Compound as u8, 0, 0, tag_type as u8, 1, 0, b'v'];
+        bytes.extend_from_slice(payload);
+        bytes.push(TagType::End as u8);
+        bytes
+    }
+
+    #[test]
+    fn bedrock_numbers_are_little_endian() {
+        let int = write_entry(Bedrock, NbtTag::Int(0x01020304));
+        assert_eq!(int, le_entry(TagType::Int, &[4, 3, 2, 1]));
+
+        let long = write_entry(Bedrock, NbtTag::Long(0x0102030405060708));
+        assert_eq!(long, le_entry(TagType::Long, &[8, 7, 6, 5, 4, 3, 2, 1]));
+
+        let float = write_entry(Bedrock, NbtTag::Float(1.5));
+        assert_eq!(float, le_entry(TagType::Float, &1.5f32.to_le_bytes()));
+
+        let ints = write_entry(Bedrock, NbtTag::IntArray(vec![-2]));
+        assert_eq!(
+            ints,
+            le_entry(TagType::IntArray, &[1, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF])
+        );
+    }
+
+    #[test]
+    fn bedrock_strings_are_utf8_with_le_lengths() {
+        // Modified UTF-8 would encode the nul as C0 80 and the emoji as a surrogate pair
+        let string = write_entry(Bedrock, NbtTag::String("\0😀".to_string()));
+        let payload = [5, 0, 0, 0xF0, 0x9F, 0x98, 0x80];
+        assert_eq!(string, le_entry(TagType::String, &payload));
+
+        let java = write_entry(Java, NbtTag::String("\0😀".to_string()));
+        assert_eq!(java[7..9], [0, 8]);
+        assert_eq!(java[9..11], [0xC0, 0x80]);
+    }
+
+    #[test]
+    fn bedrock_round_trips_like_java() {
+        let tag = NbtTag::from_snbt(
+            "{name:\"Caf\u{e9} \u{1F600}\",pos:[1.5d,-2.0d],data:{ints:[I;1,-2],longs:[L;3L],\
+             bytes:[B;1b],count:-7s,size:0.25f,seed:-9000000000L}}",
+        )
+        .unwrap();
+
+        let mut ser = Serializer::new(Vec::new()).with_flavor(Bedrock);
+        ser.serialize_tag("root", &tag).unwrap();
+        let bedrock = ser.into_inner().unwrap();
+        let (name, read) = from_bytes_flavored(&bedrock, Bedrock).unwrap();
+
+        let mut ser = Serializer::new(Vec::new());
+        ser.serialize_tag("root", &tag).unwrap();
+        let (_, java) = from_bytes(&ser.into_inner().unwrap()).unwrap();
+
+        assert_eq!(name, "root");
+        assert!(read == java);
+        assert!(read == tag);
+
+        // Java cannot make sense of little-endian lengths
+        assert!(from_bytes(&bedrock).is_err());
+    }
+
+    /// Tags every flavor must write and read back unchanged
+    fn corpus() -> Vec<NbtTag> {
+        let mut all = NbtTag::from_snbt(
+            "{byte:-128b,short:32767s,int:-2147483648,long:9223372036854775807L,\
+             float:-0.5f,double:1.0e300d,string:\"plain\",\
+             bytes:[B;-1b,0b,127b],ints:[I;-2147483648,0,2147483647],\
+             longs:[L;-9223372036854775808L,0L,1L],\
+             list:[{a:[[1s,2s],[]],b:{c:{d:[L;]}}},{}],\
+             \"\u{e9}t\u{e9}\":\"Gr\u{fc}\u{df}e \u{4e16}\u{754c} \u{1F600} \\u0000\"}",
+        )
+        .unwrap();
+        let compound = all.as_compound_mut().unwrap();
+        compound.insert("empty_bytes".to_string(), NbtTag::ByteArray(vec![]));
+        compound.insert("empty_ints".to_string(), NbtTag::IntArray(vec![]));
+        compound.insert("empty_longs".to_string(), NbtTag::LongArray(vec![]));
+        compound.insert("empty".to_string(), NbtList::new_empty().into());
+        let empty_compounds = NbtList::new(TagType::Compound).into();
+        compound.insert("empty_compounds".to_string(), empty_compounds);
+        let empty_doubles = NbtList::new(TagType::Double).into();
+        let nested = NbtList::with_tags(TagType::List, vec![empty_doubles]).unwrap();
+        compound.insert("nested_empty".to_string(), nested.into());
+
+        vec![
+            all,
+            NbtTag::Compound(NbtCompound::new()),
+            NbtTag::from_snbt("[[I;1,2],[I;]]").unwrap(),
+            NbtTag::String("\u{1F600}".to_string()),
+            NbtTag::Long(-1),
+        ]
+    }
+
+    fn round_trip<F: NbtFlavor + Copy>(flavor: F, tag: &NbtTag) -> (String, NbtTag) {
+        let mut ser = Serializer::new(Vec::new()).with_flavor(flavor);
+        ser.serialize_tag("root", tag).unwrap();
+        let bytes = ser.into_inner().unwrap();
+        from_bytes_flavored(&bytes, flavor).unwrap()
+    }
+
+    #[test]
+    fn every_flavor_round_trips_the_corpus() {
+        type RoundTrip = fn(&NbtTag) -> (String, NbtTag);
+        let flavors: [(&str, RoundTrip, &str); 4] = [
+            ("Java", |tag| round_trip(Java, tag), "root"),
+            ("JavaNetwork", |tag| round_trip(JavaNetwork, tag), ""),
+            ("Bedrock", |tag| round_trip(Bedrock, tag), "root"),
+            (
+                "BedrockNetwork",
+                |tag| round_trip(BedrockNetwork, tag),
+                "root",
+            ),
+        ];
+
+        for (flavor, round_trip, root_name) in flavors {
+            for (i, tag) in corpus().iter().enumerate() {
+                let (name, read) = round_trip(tag);
+                assert_eq!(name, root_name, "{flavor}, corpus tag {i}");
+                assert!(read == *tag, "{flavor}, corpus tag {i}: read {read}");
+            }
+        }
+    }
+}
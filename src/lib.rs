@@ -1,7 +1,121 @@
+pub mod bedrock;
+pub mod compression;
+pub mod convert;
 pub mod error;
+pub mod flavor;
+pub mod mutf8;
+pub mod nbt_ref;
 pub mod nbt_tag;
+pub mod path;
+pub mod snbt;
 
 pub mod de;
 pub mod ser;
+#[cfg(feature = "serde")]
+mod serde_with;
 
+pub use compression::Compression;
+pub use convert::{FromNbt, ToNbt};
+#[cfg(feature = "serde")]
+pub use de::serde_impl::{from_bytes, from_reader};
+pub use flavor::NbtFlavor;
+pub use nbt_ref::NbtTagRef;
+/// Builds an `NbtTag` at compile time from SNBT-like syntax.
+///
+/// Compounds, lists and `[B; ...]`, `[I; ...]` and `[L; ...]` arrays are written like in SNBT.
+/// Numbers take their tag type from an SNBT suffix (`b`, `s`, `l`, `f`, `d`) or a Rust one
+/// (`i8`, `i16`, `i32`, `i64`, `f32`, `f64`); unsuffixed integers are Ints and unsuffixed
+/// decimals are Doubles. Since `0b` starts a binary literal in Rust, write a zero byte as `0B`.
+/// Anything else is a Rust expression whose value is converted with `NbtTag::from`.
+///
+/// Literals out of range for their type, and lists mixing tag types that are known
+/// at compile time, are compile errors. The types of interpolated expressions are only
+/// known at run time, so a list whose expressions do not match its first element panics,
+/// naming the offending element.
+///
+/// ```
+/// use nbt_rust::{nbt, NbtTag};
+///
+/// let count = 64i8;
+/// let item = nbt!({
+///     id: "minecraft:stone",
+///     Count: count,
+///     tag: { Damage: 0, Unbreakable: true },
+///     Pos: [1.0d, 2.0d, -3.5d],
+///     Ints: [I; 1, 2, -3],
+/// });
+/// assert!(item["Count"] == NbtTag::Byte(64));
+/// assert!(item["tag"]["Unbreakable"] == NbtTag::Byte(1));
+/// assert_eq!(item["Pos"][2].as_f64(), Some(-3.5));
+/// assert_eq!(item["Ints"].as_int_array(), Some(&[1, 2, -3][..]));
+/// ```
+///
+/// ```compile_fail
+/// let tag = nbt_rust::nbt!([1b, 2s]);
+/// ```
+///
+/// ```should_panic
+/// let name = "Steve";
+/// // Panics: nbt! list element 1 is a String tag, but the list holds Int tags
+/// let tag = nbt_rust::nbt!([1, name]);
+/// ```
+#[cfg(feature = "macros")]
+pub use nbt_rust_macros::nbt;
+/// Derives `ToNbt` and `FromNbt` for a struct with named fields, stored as a Compound tag.
+///
+/// Fields are converted with their own `ToNbt` and `FromNbt` impls, so `Option` fields
+/// are left out when `None` and read as `None` when missing. Field attributes:
+///
+/// - `#[nbt(rename = "Name")]` stores the field under another name
+/// - `#[nbt(byte_array)]`, `#[nbt(int_array)]` and `#[nbt(long_array)]` store a `Vec`
+///   or fixed-size array (or an `Option` of one) as an array tag instead of a List
+/// - `#[nbt(default)]` uses `Default::default()` when the tag is missing
+/// - `#[nbt(flatten)]` merges the entries of a field converting to a Compound into the
+///   struct's compound, and reads the field from the struct's whole compound
+/// - `#[nbt(skip_if = path)]` leaves the field out when the function returns true
+///
+/// ```
+/// use nbt_rust::{FromNbt, Nbt, NbtTag, ToNbt};
+///
+/// #[derive(Nbt, Debug, PartialEq)]
+/// struct Entity {
+///     id: String,
+///     #[nbt(rename = "UUID", int_array)]
+///     uuid: [i32; 4],
+///     #[nbt(default, skip_if = Vec::is_empty)]
+///     #[nbt(rename = "Tags")]
+///     tags: Vec<String>,
+///     #[nbt(rename = "CustomName")]
+///     name: Option<String>,
+///     #[nbt(flatten)]
+///     position: Position,
+/// }
+///
+/// #[derive(Nbt, Debug, PartialEq)]
+/// struct Position {
+///     #[nbt(rename = "Pos")]
+///     pos: (f64, f64, f64),
+/// }
+///
+/// let entity = Entity {
+///     id: "minecraft:pig".into(),
+///     uuid: [1, 2, 3, 4],
+///     tags: vec![],
+///     name: None,
+///     position: Position { pos: (0.5, 64.0, -3.5) },
+/// };
+/// let tag = entity.to_nbt();
+/// assert!(tag["UUID"] == NbtTag::IntArray(vec![1, 2, 3, 4]));
+/// assert!(tag.get("Tags").is_none() && tag.get("CustomName").is_none());
+/// assert_eq!(tag["Pos"][1].as_f64(), Some(64.0));
+/// assert_eq!(Entity::from_nbt(&tag).unwrap(), entity);
+/// ```
+#[cfg(feature = "macros")]
+pub use nbt_rust_macros::Nbt;
 pub use nbt_tag::NbtTag;
+#[cfg(feature = "serde")]
+pub use nbt_tag::{from_value, to_value};
+#[cfg(feature = "serde")]
+pub use ser::{to_bytes, to_writer};
+#[cfg(feature = "serde")]
+pub use serde_with::{byte_array, int_array, list, long_array};
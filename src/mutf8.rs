@@ -0,0 +1,219 @@
+//! Java's Modified UTF-8, the encoding of every string in Java Edition NBT
+//!
+//! It differs from standard UTF-8 in two ways: NUL is written as the two bytes
+//! `C0 80`, and characters outside the Basic Multilingual Plane are written
+//! as a surrogate pair, each half taking three bytes.
+//!
+//! Decoding rejects the 4-byte sequences of standard UTF-8, overlong encodings
+//! other than `C0 80`, and surrogates that are not part of a pair, none of
+//! which the game writes.
+
+use std::borrow::Cow;
+
+use crate::error::{Error, Result};
+
+/// How strings are encoded in binary NBT
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// Java's Modified UTF-8, as written by the game.
+    /// Standard UTF-8 is accepted as long as it has no 4-byte sequences
+    #[default]
+    Mutf8,
+    /// Strict standard UTF-8
+    Utf8,
+}
+
+impl StringEncoding {
+    pub(crate) fn decode(self, bytes: Vec<u8>) -> Result<String> {
+        match self {
+            StringEncoding::Mutf8 => match String::from_utf8(bytes) {
+                Ok(string) if !string.bytes().any(|b| b >= 0xF0) => Ok(string),
+                Ok(string) => decode(string.as_bytes()).map(Cow::into_owned),
+                Err(e) => decode(e.as_bytes()).map(Cow::into_owned),
+            },
+            StringEncoding::Utf8 => Ok(String::from_utf8(bytes)?),
+        }
+    }
+
+    pub(crate) fn encode(self, string: &str) -> Cow<'_, [u8]> {
+        match self {
+            StringEncoding::Mutf8 => encode(string),
+            StringEncoding::Utf8 => Cow::Borrowed(string.as_bytes()),
+        }
+    }
+}
+
+/// Decodes Modified UTF-8, borrowing the input when it is also valid UTF-8
+/// (which it is unless it encodes NUL or characters outside the BMP)
+///
+/// ```
+/// use nbt_rust::mutf8;
+///
+/// assert_eq!(mutf8::decode(b"a\xC0\x80b").unwrap(), "a\0b");
+/// assert_eq!(mutf8::decode(b"\xED\xA0\xBD\xED\xB8\x80").unwrap(), "\u{1F600}");
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>> {
+    // Valid UTF-8 never contains C0 80 or encoded surrogates, so without
+    // 4-byte sequences it means the same in both encodings
+    if !bytes.iter().any(|&b| b >= 0xF0) {
+        if let Ok(string) = std::str::from_utf8(bytes) {
+            return Ok(Cow::Borrowed(string));
+        }
+    }
+
+    let mut string = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let (unit, len) = read_unit(bytes, i)?;
+
+        let c = match unit {
+            0xD800..=0xDBFF => {
+                let low = read_unit(bytes, i + len)
+                    .ok()
+                    .filter(|(low, _)| (0xDC00..=0xDFFF).contains(low));
+                let Some((low, low_len)) = low else {
+                    return Err(Error::Mutf8(i));
+                };
+
+                i += low_len;
+                0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(Error::Mutf8(i)),
+            _ => unit,
+        };
+
+        string.push(char::from_u32(c).ok_or(Error::Mutf8(i))?);
+        i += len;
+    }
+
+    Ok(Cow::Owned(string))
+}
+
+/// Reads one UTF-16 code unit, rejecting overlong forms other than `C0 80`
+fn read_unit(bytes: &[u8], i: usize) -> Result<(u32, usize)> {
+    let continuation = |j: usize| match bytes.get(i + j) {
+        Some(&b) if b & 0xC0 == 0x80 => Ok((b & 0x3F) as u32),
+        _ => Err(Error::Mutf8(i)),
+    };
+
+    let Some(&first) = bytes.get(i) else {
+        return Err(Error::Mutf8(i));
+    };
+    let first = first as u32;
+
+    let (unit, len) = match first {
+        0x00..=0x7F => return Ok((first, 1)),
+        0xC0..=0xDF => (((first & 0x1F) << 6) | continuation(1)?, 2),
+        0xE0..=0xEF => (
+            ((first & 0x0F) << 12) | (continuation(1)? << 6) | continuation(2)?,
+            3,
+        ),
+        _ => return Err(Error::Mutf8(i)),
+    };
+
+    let overlong = match len {
+        2 => unit < 0x80 && unit != 0,
+        _ => unit < 0x800,
+    };
+    match overlong {
+        true => Err(Error::Mutf8(i)),
+        false => Ok((unit, len)),
+    }
+}
+
+/// Encodes a string as Modified UTF-8, borrowing it when no conversion is needed
+///
+/// ```
+/// use nbt_rust::mutf8;
+///
+/// assert_eq!(&*mutf8::encode("a\0b"), b"a\xC0\x80b");
+/// assert_eq!(&*mutf8::encode("plain"), b"plain");
+/// ```
+pub fn encode(string: &str) -> Cow<'_, [u8]> {
+    if !string.bytes().any(|b| b == 0 || b >= 0xF0) {
+        return Cow::Borrowed(string.as_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(string.len() + 2);
+    for c in string.chars() {
+        match c {
+            '\0' => bytes.extend([0xC0, 0x80]),
+            '\u{10000}'.. => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    let unit = *unit as u32;
+                    bytes.extend([
+                        0xE0 | (unit >> 12) as u8,
+                        0x80 | ((unit >> 6) & 0x3F) as u8,
+                        0x80 | (unit & 0x3F) as u8,
+                    ]);
+                }
+            }
+            _ => bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    Cow::Owned(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_nul_and_surrogate_pairs() {
+        assert_eq!(decode(b"\xC0\x80").unwrap(), "\0");
+        // U+1F600 is the surrogate pair D83D DE00
+        let emoji = b"[\xED\xA0\xBD\xED\xB8\x80]";
+        assert_eq!(decode(emoji).unwrap(), "[\u{1F600}]");
+        assert_eq!(
+            &*encode("[\u{1F600}]\0"),
+            b"[\xED\xA0\xBD\xED\xB8\x80]\xC0\x80"
+        );
+        assert!(matches!(
+            decode("é€".as_bytes()).unwrap(),
+            Cow::Borrowed("é€")
+        ));
+    }
+
+    #[test]
+    fn rejects_four_byte_sequences() {
+        let error = decode("a\u{1F600}".as_bytes()).unwrap_err();
+        assert!(matches!(error, Error::Mutf8(1)));
+
+        let error = StringEncoding::Mutf8
+            .decode("\u{1F600}".into())
+            .unwrap_err();
+        assert!(matches!(error, Error::Mutf8(0)));
+    }
+
+    #[test]
+    fn rejects_overlong_encodings() {
+        // 'A' in two bytes, '/' in three and NUL in three
+        for bytes in [&b"\xC1\x81"[..], b"\xE0\x80\xAF", b"\xE0\x80\x80"] {
+            assert!(matches!(decode(bytes), Err(Error::Mutf8(0))), "{bytes:x?}");
+        }
+        assert!(matches!(decode(b"ab\xC0\x81"), Err(Error::Mutf8(2))));
+    }
+
+    #[test]
+    fn rejects_unpaired_surrogates() {
+        assert!(matches!(decode(b"\xED\xA0\xBD"), Err(Error::Mutf8(0))));
+        assert!(matches!(decode(b"x\xED\xB8\x80"), Err(Error::Mutf8(1))));
+    }
+
+    #[test]
+    fn utf8_mode_stays_strict() {
+        let utf8 = StringEncoding::Utf8;
+        assert_eq!(utf8.decode("\u{1F600}".into()).unwrap(), "\u{1F600}");
+        assert!(matches!(
+            utf8.decode(b"\xC0\x80".to_vec()),
+            Err(Error::Utf8(_))
+        ));
+        assert!(matches!(
+            utf8.decode(b"\xED\xA0\xBD\xED\xB8\x80".to_vec()),
+            Err(Error::Utf8(_))
+        ));
+        assert_eq!(utf8.encode("\0"), &b"\0"[..]);
+    }
+}
@@ -0,0 +1,678 @@
+//! Borrowed NBT tags, parsed from a byte slice without copying
+//!
+//! Strings and arrays of an `NbtTagRef` point into the input, and the numbers of
+//! Int and Long arrays are only decoded when they are accessed. This makes scanning
+//! many files for a few values much cheaper than building an `NbtTag` for each.
+//!
+//! Only uncompressed Java Edition NBT is supported; decompress the input first.
+//!
+//! ```
+//! use nbt_rust::nbt_ref::NbtTagRef;
+//!
+//! # fn main() -> nbt_rust::error::Result<()> {
+//! let bytes = [
+//!     0x0a, 0x00, 0x00, // Root compound without a name
+//!     0x0b, 0x00, 0x01, b'a', 0x00, 0x00, 0x00, 0x02, // IntArray "a" of 2 ints
+//!     0x00, 0x00, 0x00, 0x07, 0xff, 0xff, 0xff, 0xff,
+//!     0x00,
+//! ];
+//!
+//! let (_, root) = NbtTagRef::from_bytes(&bytes)?;
+//! if let NbtTagRef::Compound(root) = &root {
+//!     if let Some(NbtTagRef::IntArray(a)) = root.get("a") {
+//!         assert_eq!(a.iter().collect::<Vec<_>>(), [7, -1]);
+//!     }
+//! }
+//! let owned = root.to_owned();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{borrow::Cow, ops::Deref};
+
+use crate::{
+    de::{tag_overhead, ReadLimits, ELEMENT_OVERHEAD, ENTRY_OVERHEAD, STRING_OVERHEAD},
+    error::{Error, Result},
+    mutf8,
+    nbt_tag::{NbtList, TagType},
+    NbtTag,
+};
+
+/// A tag borrowing its strings and arrays from the bytes it was parsed from
+#[derive(Clone)]
+pub enum NbtTagRef<'a> {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(&'a [u8]),
+    /// Borrowed unless the string had to be converted from Modified UTF-8
+    String(Cow<'a, str>),
+    List(NbtListRef<'a>),
+    Compound(NbtCompoundRef<'a>),
+    IntArray(IntArrayRef<'a>),
+    LongArray(LongArrayRef<'a>),
+}
+
+impl<'a> NbtTagRef<'a> {
+    /// Parses a root tag and its name from uncompressed Java NBT
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Cow<'a, str>, NbtTagRef<'a>)> {
+        Self::from_bytes_with_limits(bytes, ReadLimits::default())
+    }
+
+    /// Parses a root tag like `from_bytes`, enforcing the given limits
+    pub fn from_bytes_with_limits(
+        bytes: &'a [u8],
+        limits: ReadLimits,
+    ) -> Result<(Cow<'a, str>, NbtTagRef<'a>)> {
+        let mut parser = Parser {
+            bytes,
+            pos: 0,
+            limits,
+            depth: 0,
+            charged: 0,
+        };
+        parser
+            .read_root()
+            .map_err(|e| e.at_offset(parser.pos as u64))
+    }
+
+    /// Returns the tag prefix of the tag
+    pub const fn tag_type(&self) -> TagType {
+        match self {
+            NbtTagRef::End => TagType::End,
+            NbtTagRef::Byte(_) => TagType::Byte,
+            NbtTagRef::Short(_) => TagType::Short,
+            NbtTagRef::Int(_) => TagType::Int,
+            NbtTagRef::Long(_) => TagType::Long,
+            NbtTagRef::Float(_) => TagType::Float,
+            NbtTagRef::Double(_) => TagType::Double,
+            NbtTagRef::ByteArray(_) => TagType::ByteArray,
+            NbtTagRef::String(_) => TagType::String,
+            NbtTagRef::List(_) => TagType::List,
+            NbtTagRef::Compound(_) => TagType::Compound,
+            NbtTagRef::IntArray(_) => TagType::IntArray,
+            NbtTagRef::LongArray(_) => TagType::LongArray,
+        }
+    }
+
+    /// Copies the tag and everything it borrows into an owned `NbtTag`
+    pub fn to_owned(&self) -> NbtTag {
+        match self {
+            NbtTagRef::End => NbtTag::End,
+            NbtTagRef::Byte(v) => NbtTag::Byte(*v),
+            NbtTagRef::Short(v) => NbtTag::Short(*v),
+            NbtTagRef::Int(v) => NbtTag::Int(*v),
+            NbtTagRef::Long(v) => NbtTag::Long(*v),
+            NbtTagRef::Float(v) => NbtTag::Float(*v),
+            NbtTagRef::Double(v) => NbtTag::Double(*v),
+            NbtTagRef::ByteArray(v) => NbtTag::ByteArray(v.to_vec()),
+            NbtTagRef::String(v) => NbtTag::String(v.to_string()),
+            NbtTagRef::List(v) => NbtTag::List(v.to_owned()),
+            NbtTagRef::Compound(v) => NbtTag::Compound(
+                v.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_owned()))
+                    .collect(),
+            ),
+            NbtTagRef::IntArray(v) => NbtTag::IntArray(v.to_vec()),
+            NbtTagRef::LongArray(v) => NbtTag::LongArray(v.to_vec()),
+        }
+    }
+}
+
+/// The payload of a borrowed List tag
+#[derive(Clone)]
+pub struct NbtListRef<'a> {
+    elem_type: TagType,
+    tags: Vec<NbtTagRef<'a>>,
+}
+
+impl NbtListRef<'_> {
+    /// Returns the type of the list elements
+    pub const fn elem_type(&self) -> TagType {
+        self.elem_type
+    }
+
+    /// Copies the list into an owned `NbtList`
+    pub fn to_owned(&self) -> NbtList {
+        let tags = self.tags.iter().map(NbtTagRef::to_owned).collect();
+        NbtList::with_tags(self.elem_type, tags).expect("parsed list elements share its type")
+    }
+}
+
+impl<'a> Deref for NbtListRef<'a> {
+    type Target = [NbtTagRef<'a>];
+
+    fn deref(&self) -> &[NbtTagRef<'a>] {
+        &self.tags
+    }
+}
+
+/// The payload of a borrowed Compound tag, in the order the tags were read
+#[derive(Clone, Default)]
+pub struct NbtCompoundRef<'a> {
+    tags: Vec<(Cow<'a, str>, NbtTagRef<'a>)>,
+}
+
+impl<'a> NbtCompoundRef<'a> {
+    /// Returns the tag with the given name
+    ///
+    /// Tags are kept in a `Vec` in the order they were read, so this is a
+    /// linear scan. To look up many names in a large compound, collect
+    /// [`iter`](Self::iter) into a map first.
+    pub fn get(&self, name: &str) -> Option<&NbtTagRef<'a>> {
+        self.tags.iter().find(|(k, _)| k == name).map(|(_, v)| v)
+    }
+
+    /// Iterates over the names and tags of the compound
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NbtTagRef<'a>)> {
+        self.tags.iter().map(|(k, v)| (k.as_ref(), v))
+    }
+
+    /// Returns the number of tags in the compound
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns `true` if the compound holds no tags
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// A borrowed IntArray, whose big-endian ints are decoded on access
+#[derive(Clone, Copy)]
+pub struct IntArrayRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> IntArrayRef<'a> {
+    /// Returns the number of ints in the array
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 4
+    }
+
+    /// Returns `true` if the array is empty
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Decodes the int at the given index
+    pub fn get(&self, index: usize) -> Option<i32> {
+        let start = index.checked_mul(4)?;
+        let c = self.bytes.get(start..)?.get(..4)?;
+        Some(i32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+    }
+
+    /// Decodes the ints one by one
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = i32> + 'a {
+        self.bytes
+            .chunks_exact(4)
+            .map(|c| i32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+    }
+
+    /// The raw big-endian bytes of the array
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes the whole array
+    pub fn to_vec(&self) -> Vec<i32> {
+        self.iter().collect()
+    }
+}
+
+/// A borrowed LongArray, whose big-endian longs are decoded on access
+#[derive(Clone, Copy)]
+pub struct LongArrayRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> LongArrayRef<'a> {
+    /// Returns the number of longs in the array
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 8
+    }
+
+    /// Returns `true` if the array is empty
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Decodes the long at the given index
+    pub fn get(&self, index: usize) -> Option<i64> {
+        let start = index.checked_mul(8)?;
+        let c = self.bytes.get(start..)?.get(..8)?;
+        Some(i64::from_be_bytes([
+            c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7],
+        ]))
+    }
+
+    /// Decodes the longs one by one
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = i64> + 'a {
+        self.bytes
+            .chunks_exact(8)
+            .map(|c| i64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+    }
+
+    /// The raw big-endian bytes of the array
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes the whole array
+    pub fn to_vec(&self) -> Vec<i64> {
+        self.iter().collect()
+    }
+}
+
+/// Reads borrowed tags from a slice, applying the same checks as `Decoder`
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    limits: ReadLimits,
+    depth: usize,
+    /// Bytes charged against `limits.max_bytes`, like `Decoder` does
+    charged: u64,
+}
+
+impl<'a> Parser<'a> {
+    fn read_root(&mut self) -> Result<(Cow<'a, str>, NbtTagRef<'a>)> {
+        let prefix = self.read_entry_type()?;
+        if prefix == TagType::End {
+            return Ok((Cow::Borrowed(""), NbtTagRef::End));
+        }
+
+        let name = self.read_string()?;
+        let value = self.read_payload(prefix)?;
+        Ok((name, value))
+    }
+
+    fn read_payload(&mut self, prefix: TagType) -> Result<NbtTagRef<'a>> {
+        Ok(match prefix {
+            TagType::End => NbtTagRef::End,
+            TagType::Byte => NbtTagRef::Byte(self.take::<1>()?[0] as i8),
+            TagType::Short => NbtTagRef::Short(i16::from_be_bytes(self.take()?)),
+            TagType::Int => NbtTagRef::Int(i32::from_be_bytes(self.take()?)),
+            TagType::Long => NbtTagRef::Long(i64::from_be_bytes(self.take()?)),
+            TagType::Float => NbtTagRef::Float(f32::from_be_bytes(self.take()?)),
+            TagType::Double => NbtTagRef::Double(f64::from_be_bytes(self.take()?)),
+            TagType::ByteArray => {
+                let len = self.read_len()?;
+                NbtTagRef::ByteArray(self.read_slice(len)?)
+            }
+            TagType::String => NbtTagRef::String(self.read_string()?),
+            TagType::List => NbtTagRef::List(self.read_list()?),
+            TagType::Compound => NbtTagRef::Compound(self.read_compound()?),
+            TagType::IntArray => {
+                let len = self.read_len()?;
+                let len = len
+                    .checked_mul(4)
+                    .ok_or(Error::LengthLimitExceeded(usize::MAX / 4))?;
+                NbtTagRef::IntArray(IntArrayRef {
+                    bytes: self.read_slice(len)?,
+                })
+            }
+            TagType::LongArray => {
+                let len = self.read_len()?;
+                let len = len
+                    .checked_mul(8)
+                    .ok_or(Error::LengthLimitExceeded(usize::MAX / 8))?;
+                NbtTagRef::LongArray(LongArrayRef {
+                    bytes: self.read_slice(len)?,
+                })
+            }
+        })
+    }
+
+    fn read_list(&mut self) -> Result<NbtListRef<'a>> {
+        let elem_type = self.read_tag_type()?;
+        let len = self.read_len()?;
+        if elem_type == TagType::End && len > 0 {
+            return Err(Error::Unsupported("List of End tags"));
+        }
+        self.charge(len as u64 * (ELEMENT_OVERHEAD + tag_overhead(elem_type)))?;
+
+        self.enter()?;
+        let mut tags = Vec::new();
+        for i in 0..len {
+            tags.push(self.read_payload(elem_type).map_err(|e| e.in_index(i))?);
+        }
+        self.depth -= 1;
+
+        Ok(NbtListRef { elem_type, tags })
+    }
+
+    fn read_compound(&mut self) -> Result<NbtCompoundRef<'a>> {
+        let mut tags = Vec::new();
+
+        self.enter()?;
+        loop {
+            let prefix = self.read_entry_type()?;
+            if prefix == TagType::End {
+                break;
+            }
+
+            let name = self.read_string()?;
+            let value = self.read_payload(prefix).map_err(|e| e.in_key(&name))?;
+            tags.push((name, value));
+        }
+        self.depth -= 1;
+
+        Ok(NbtCompoundRef { tags })
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::DepthLimitExceeded(self.limits.max_depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Charges `len` bytes against the quota, failing once it is exceeded
+    #[inline]
+    fn charge(&mut self, len: u64) -> Result<()> {
+        match self.charged.checked_add(len) {
+            Some(total) if total <= self.limits.max_bytes => {
+                self.charged = total;
+                Ok(())
+            }
+            _ => Err(Error::ByteLimitExceeded(self.limits.max_bytes)),
+        }
+    }
+
+    #[inline]
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8]> {
+        self.charge(len as u64)?;
+
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        };
+
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    #[inline]
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.read_slice(N)?);
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn read_tag_type(&mut self) -> Result<TagType> {
+        self.take::<1>()?[0].try_into()
+    }
+
+    /// Reads the type of a root tag or compound entry, charging the
+    /// overhead of the entry and its value
+    fn read_entry_type(&mut self) -> Result<TagType> {
+        let tag_type = self.read_tag_type()?;
+        if tag_type != TagType::End {
+            self.charge(ENTRY_OVERHEAD + tag_overhead(tag_type))?;
+        }
+        Ok(tag_type)
+    }
+
+    /// Reads the i32 length prefix of a list or array
+    fn read_len(&mut self) -> Result<usize> {
+        let len = i32::from_be_bytes(self.take()?);
+        if len < 0 {
+            return Err(Error::NegativeLength(len));
+        }
+
+        let len = len as usize;
+        if len > self.limits.max_len {
+            return Err(Error::LengthLimitExceeded(self.limits.max_len));
+        }
+        Ok(len)
+    }
+
+    fn read_string(&mut self) -> Result<Cow<'a, str>> {
+        let len = u16::from_be_bytes(self.take()?) as usize;
+        self.charge(STRING_OVERHEAD + len as u64)?;
+        mutf8::decode(self.read_slice(len)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de::from_bytes, ser::Serializer};
+
+    fn write(tag: &NbtTag) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        Serializer::new(&mut bytes)
+            .serialize_tag("root", tag)
+            .unwrap();
+        bytes
+    }
+
+    /// A tree holding every tag type, with strings that need no conversion and one that does
+    fn every_type() -> NbtTag {
+        NbtTag::from_snbt(
+            "{byte:1b,short:-2s,int:3,long:-4L,float:0.5f,double:-1.5d,\
+             bytes:[B;1b,-1b],string:\"caf\u{e9}\",nul:\"a\\u0000b\",\
+             list:[[I;1],[I;]],empty:[],compound:{nested:{}},\
+             ints:[I;7,-1],longs:[L;-9223372036854775808L,5L]}",
+        )
+        .unwrap()
+    }
+
+    /// Whether the slice lies within the input
+    fn borrows_from(input: &[u8], slice: &[u8]) -> bool {
+        input.as_ptr_range().contains(&slice.as_ptr())
+    }
+
+    fn compound<'a, 'b>(tag: &'b NbtTagRef<'a>) -> &'b NbtCompoundRef<'a> {
+        match tag {
+            NbtTagRef::Compound(compound) => compound,
+            _ => panic!("not a Compound"),
+        }
+    }
+
+    fn root_long_array(len: i32, longs: &[i64]) -> Vec<u8> {
+        let mut bytes = vec![0x0c, 0x00, 0x00];
+        bytes.extend(len.to_be_bytes());
+        bytes.extend(longs.iter().flat_map(|l| l.to_be_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn arrays_are_decoded_on_access() {
+        let bytes = root_long_array(2, &[i64::MIN, 5]);
+        let (_, tag) = NbtTagRef::from_bytes(&bytes).unwrap();
+        let NbtTagRef::LongArray(longs) = tag else {
+            panic!("not a LongArray");
+        };
+        assert_eq!(longs.len(), 2);
+        assert_eq!(longs.get(0), Some(i64::MIN));
+        assert_eq!(longs.get(2), None);
+        assert_eq!(longs.get(usize::MAX / 4), None);
+        assert_eq!(longs.get(usize::MAX), None);
+    }
+
+    #[test]
+    fn truncated_arrays_are_rejected() {
+        let bytes = root_long_array(i32::MAX, &[1, 2]);
+        let limits = ReadLimits::unlimited();
+        let error = NbtTagRef::from_bytes_with_limits(&bytes, limits)
+            .err()
+            .unwrap();
+        assert!(matches!(error.inner(), Error::Io(_)));
+    }
+
+    #[test]
+    fn to_owned_matches_the_decoder() {
+        let bytes = write(&every_type());
+        let (name, tag) = NbtTagRef::from_bytes(&bytes).unwrap();
+        let (owned_name, owned) = from_bytes(&bytes).unwrap();
+        assert_eq!(name, owned_name);
+        assert!(tag.to_owned() == owned);
+        assert!(owned == every_type());
+    }
+
+    #[test]
+    fn strings_and_arrays_borrow_from_the_input() {
+        let bytes = write(&every_type());
+        let (name, tag) = NbtTagRef::from_bytes(&bytes).unwrap();
+        assert!(matches!(name, Cow::Borrowed("root")));
+        let root = compound(&tag);
+
+        let Some(NbtTagRef::String(Cow::Borrowed(string))) = root.get("string") else {
+            panic!("the string was copied");
+        };
+        assert_eq!(*string, "caf\u{e9}");
+        assert!(borrows_from(&bytes, string.as_bytes()));
+
+        // C0 80 is not UTF-8, so this one has to be converted
+        let Some(NbtTagRef::String(Cow::Owned(nul))) = root.get("nul") else {
+            panic!("Modified UTF-8 was borrowed");
+        };
+        assert_eq!(nul, "a\0b");
+
+        let Some(NbtTagRef::ByteArray(array)) = root.get("bytes") else {
+            panic!("not a ByteArray");
+        };
+        assert_eq!(*array, [1, 255]);
+        assert!(borrows_from(&bytes, array));
+
+        let Some(NbtTagRef::Compound(nested)) = root.get("compound") else {
+            panic!("not a Compound");
+        };
+        let (key, _) = nested.iter().next().unwrap();
+        assert!(borrows_from(&bytes, key.as_bytes()));
+    }
+
+    #[test]
+    fn compound_lookups() {
+        let bytes = write(&every_type());
+        let (_, tag) = NbtTagRef::from_bytes(&bytes).unwrap();
+        let root = compound(&tag);
+        assert_eq!(root.len(), 14);
+        assert!(matches!(root.get("int"), Some(NbtTagRef::Int(3))));
+        assert!(matches!(root.get("double"), Some(NbtTagRef::Double(v)) if *v == -1.5));
+        assert!(root.get("missing").is_none());
+        assert!(root.get("Int").is_none());
+
+        let Some(NbtTagRef::Compound(nested)) = root.get("compound") else {
+            panic!("not a Compound");
+        };
+        let inner = compound(nested.get("nested").unwrap());
+        assert!(inner.is_empty());
+        assert!(inner.get("").is_none());
+    }
+
+    #[test]
+    fn list_element_types() {
+        let bytes = write(&every_type());
+        let (_, tag) = NbtTagRef::from_bytes(&bytes).unwrap();
+        let root = compound(&tag);
+
+        let Some(NbtTagRef::List(empty)) = root.get("empty") else {
+            panic!("not a List");
+        };
+        assert_eq!(empty.elem_type(), TagType::End);
+        assert!(empty.is_empty());
+        assert_eq!(empty.to_owned().elem_type(), TagType::End);
+
+        let Some(NbtTagRef::List(list)) = root.get("list") else {
+            panic!("not a List");
+        };
+        assert_eq!(list.elem_type(), TagType::IntArray);
+        assert_eq!(list.len(), 2);
+        assert!(list.iter().all(|t| t.tag_type() == TagType::IntArray));
+    }
+
+    #[test]
+    fn int_and_long_arrays_are_indexed_lazily() {
+        let bytes = write(&every_type());
+        let (_, tag) = NbtTagRef::from_bytes(&bytes).unwrap();
+        let root = compound(&tag);
+
+        let Some(NbtTagRef::IntArray(ints)) = root.get("ints") else {
+            panic!("not an IntArray");
+        };
+        assert_eq!((ints.len(), ints.is_empty()), (2, false));
+        assert_eq!(ints.get(1), Some(-1));
+        assert_eq!(ints.get(2), None);
+        assert_eq!(ints.as_bytes(), [0, 0, 0, 7, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(borrows_from(&bytes, ints.as_bytes()));
+        assert_eq!(ints.iter().len(), 2);
+
+        let Some(NbtTagRef::LongArray(longs)) = root.get("longs") else {
+            panic!("not a LongArray");
+        };
+        assert_eq!(longs.get(1), Some(5));
+        assert_eq!(longs.to_vec(), [i64::MIN, 5]);
+
+        let Some(NbtTagRef::List(list)) = root.get("list") else {
+            panic!("not a List");
+        };
+        let NbtTagRef::IntArray(empty) = &list[1] else {
+            panic!("not an IntArray");
+        };
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(0), None);
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let bytes = write(&every_type());
+        for len in 0..bytes.len() {
+            let error = NbtTagRef::from_bytes(&bytes[..len]).err();
+            let error = error.unwrap_or_else(|| panic!("{len} bytes parsed"));
+            assert!(
+                matches!(error.inner(), Error::Io(_)),
+                "{len} bytes: {error}"
+            );
+            assert!(error.offset().unwrap() <= len as u64);
+        }
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let bytes = write(&every_type());
+        let parse = |limits| {
+            NbtTagRef::from_bytes_with_limits(&bytes, limits)
+                .err()
+                .unwrap()
+        };
+
+        let error = parse(ReadLimits {
+            max_depth: 2,
+            ..ReadLimits::unlimited()
+        });
+        assert!(matches!(error.inner(), Error::DepthLimitExceeded(2)));
+
+        let error = parse(ReadLimits {
+            max_len: 1,
+            ..ReadLimits::unlimited()
+        });
+        assert!(matches!(error.inner(), Error::LengthLimitExceeded(1)));
+
+        let error = parse(ReadLimits {
+            max_bytes: 100,
+            ..ReadLimits::unlimited()
+        });
+        assert!(matches!(error.inner(), Error::ByteLimitExceeded(100)));
+
+        // Negative lengths and non-empty lists of End tags
+        let negative = root_long_array(-1, &[]);
+        let error = NbtTagRef::from_bytes(&negative).err().unwrap();
+        assert!(matches!(error.inner(), Error::NegativeLength(-1)));
+        let ends = [0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let error = NbtTagRef::from_bytes(&ends).err().unwrap();
+        assert!(matches!(error.inner(), Error::Unsupported(_)));
+    }
+}
@@ -1,13 +1,21 @@
 use std::{
     collections::HashMap,
     fmt::{self, Formatter},
+    mem,
+    ops::{self, Deref},
+    slice,
 };
 
-use crate::error::Error;
+use crate::{error::Error, snbt::SnbtWriter};
+
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "serde")]
+pub use serde_impl::{from_value, to_value};
 
 /// In the binary format, each tag is prefixed with a single byte
 /// which identifies its type. The tag prefixes are listed below.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum TagType {
     End = 0x00,
@@ -48,11 +56,21 @@ impl TryFrom<u8> for TagType {
     }
 }
 
+/// The map backing Compound tags.
+///
+/// With the `preserve_order` feature (enabled by default) it keeps keys in
+/// insertion order, so a compound that is read and written back again
+/// produces exactly the same bytes. Without it, a `HashMap` is used.
+#[cfg(feature = "preserve_order")]
+pub type NbtCompound = indexmap::IndexMap<String, NbtTag>;
+#[cfg(not(feature = "preserve_order"))]
+pub type NbtCompound = HashMap<String, NbtTag>;
+
 /// The NbtTag enum represents all the possible NBT tags.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum NbtTag {
     End,
-    Byte(u8),
+    Byte(i8),
     Short(i16),
     Int(i32),
     Long(i64),
@@ -60,12 +78,337 @@ pub enum NbtTag {
     Double(f64),
     ByteArray(Vec<u8>),
     String(String),
-    List(Vec<NbtTag>),
-    Compound(HashMap<String, NbtTag>),
+    List(NbtList),
+    Compound(NbtCompound),
     IntArray(Vec<i32>),
     LongArray(Vec<i64>),
 }
 
+/// The payload of a List tag: unnamed tags that all share one type.
+///
+/// The element type is kept even when the list is empty, so an empty
+/// `List<Compound>` is written back as such. Minecraft itself writes
+/// empty lists with the End element type, which is what `new_empty` creates.
+///
+/// Every way of adding or replacing elements checks their type, so elements
+/// are modified through `TagMut` rather than `&mut NbtTag`.
+#[derive(Clone, PartialEq)]
+pub struct NbtList {
+    elem_type: TagType,
+    tags: Vec<NbtTag>,
+}
+
+impl NbtList {
+    /// Creates an empty list whose elements will be of the given type
+    pub fn new(elem_type: TagType) -> Self {
+        Self {
+            elem_type,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Creates an empty list with the End element type
+    pub fn new_empty() -> Self {
+        Self::new(TagType::End)
+    }
+
+    /// Creates a list of the given element type, failing if any tag is of another type.
+    /// End tags only terminate compounds, so no list can hold them.
+    pub fn with_tags(elem_type: TagType, tags: Vec<NbtTag>) -> Result<Self, Error> {
+        if tags
+            .iter()
+            .any(|t| t.tag_type() != elem_type || t.tag_type() == TagType::End)
+        {
+            return Err(Error::ElementTypesDiffer);
+        }
+        Ok(Self { elem_type, tags })
+    }
+
+    /// Returns the type of the list elements
+    pub const fn elem_type(&self) -> TagType {
+        self.elem_type
+    }
+
+    /// Appends a tag to the list.
+    ///
+    /// An empty End-typed list adopts the type of its first tag;
+    /// otherwise the tag must be of the list's element type.
+    /// End tags are always rejected.
+    pub fn push(&mut self, tag: NbtTag) -> Result<(), Error> {
+        self.accept(tag.tag_type())?;
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    /// Inserts a tag at the given position, with the same type rules as `push`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the list.
+    pub fn insert(&mut self, index: usize, tag: NbtTag) -> Result<(), Error> {
+        self.accept(tag.tag_type())?;
+        self.tags.insert(index, tag);
+        Ok(())
+    }
+
+    /// Checks that a tag of the given type can be added, adopting its type if the list is empty
+    fn accept(&mut self, tag_type: TagType) -> Result<(), Error> {
+        if tag_type == TagType::End {
+            return Err(Error::ElementTypesDiffer);
+        }
+        if self.tags.is_empty() && self.elem_type == TagType::End {
+            self.elem_type = tag_type;
+        } else if tag_type != self.elem_type {
+            return Err(Error::ElementTypesDiffer);
+        }
+        Ok(())
+    }
+
+    /// Replaces the tag at the given position, returning the old one.
+    /// Fails if the tag is not of the list's element type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, tag: NbtTag) -> Result<NbtTag, Error> {
+        let len = self.tags.len();
+        match self.get_mut(index) {
+            Some(mut elem) => elem.set(tag),
+            None => panic!("index {index} out of bounds for a list of length {len}"),
+        }
+    }
+
+    /// Returns the tag at the given position for modification
+    pub fn get_mut(&mut self, index: usize) -> Option<TagMut<'_>> {
+        let elem_type = self.elem_type;
+        self.tags
+            .get_mut(index)
+            .map(|tag| TagMut::new(tag, Some(elem_type)))
+    }
+
+    /// Returns an iterator over the tags for modification
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            tags: self.tags.iter_mut(),
+            elem_type: self.elem_type,
+        }
+    }
+
+    /// Removes and returns the tag at the given position.
+    /// Like in Minecraft, a list emptied this way gets the End element type again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> NbtTag {
+        let tag = self.tags.remove(index);
+        self.forget_type_if_empty();
+        tag
+    }
+
+    /// Keeps only the tags for which the predicate returns `true`
+    pub fn retain(&mut self, f: impl FnMut(&NbtTag) -> bool) {
+        self.tags.retain(f);
+        self.forget_type_if_empty();
+    }
+
+    /// Removes all tags, resetting the element type to End
+    pub fn clear(&mut self) {
+        self.tags.clear();
+        self.forget_type_if_empty();
+    }
+
+    fn forget_type_if_empty(&mut self) {
+        if self.tags.is_empty() {
+            self.elem_type = TagType::End;
+        }
+    }
+
+    /// Consumes the list and returns its tags
+    pub fn into_inner(self) -> Vec<NbtTag> {
+        self.tags
+    }
+}
+
+impl Deref for NbtList {
+    type Target = [NbtTag];
+
+    fn deref(&self) -> &[NbtTag] {
+        &self.tags
+    }
+}
+
+impl<'a> IntoIterator for &'a NbtList {
+    type Item = &'a NbtTag;
+    type IntoIter = std::slice::Iter<'a, NbtTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut NbtList {
+    type Item = TagMut<'a>;
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for NbtList {
+    type Item = NbtTag;
+    type IntoIter = std::vec::IntoIter<NbtTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.into_iter()
+    }
+}
+
+/// Builds a list typed after its first tag, or an End-typed list if empty.
+/// Fails like `with_tags` if the other tags are of another type.
+impl TryFrom<Vec<NbtTag>> for NbtList {
+    type Error = Error;
+
+    fn try_from(tags: Vec<NbtTag>) -> Result<Self, Error> {
+        let elem_type = tags.first().map_or(TagType::End, NbtTag::tag_type);
+        Self::with_tags(elem_type, tags)
+    }
+}
+
+impl NbtList {
+    /// Builds a list typed after its first tag without checking the others,
+    /// for conversions that cannot fail. Mixed tag types are still reported
+    /// when the list is serialized.
+    pub(crate) fn from_tags_unchecked(tags: Vec<NbtTag>) -> Self {
+        let elem_type = tags.first().map_or(TagType::End, NbtTag::tag_type);
+        Self { elem_type, tags }
+    }
+
+    /// Builds the list of an `nbt!` invocation, panicking at the invocation
+    /// if an interpolated expression has another type than the first element
+    #[doc(hidden)]
+    #[track_caller]
+    pub fn from_macro_elements(tags: Vec<NbtTag>) -> Self {
+        let elem_type = tags.first().map_or(TagType::End, NbtTag::tag_type);
+        if let Some(index) = tags.iter().position(|t| t.tag_type() == TagType::End) {
+            panic!("nbt! list element {index} is an End tag, which lists cannot hold");
+        }
+        if let Some((index, tag)) = tags
+            .iter()
+            .enumerate()
+            .find(|(_, t)| t.tag_type() != elem_type)
+        {
+            panic!(
+                "nbt! list element {index} is a {:?} tag, but the list holds {elem_type:?} tags",
+                tag.tag_type()
+            );
+        }
+        Self { elem_type, tags }
+    }
+}
+
+/// Mutable iterator over the tags of an `NbtList`, created by `NbtList::iter_mut`
+pub struct IterMut<'a> {
+    tags: slice::IterMut<'a, NbtTag>,
+    elem_type: TagType,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = TagMut<'a>;
+
+    fn next(&mut self) -> Option<TagMut<'a>> {
+        let elem_type = self.elem_type;
+        self.tags
+            .next()
+            .map(|tag| TagMut::new(tag, Some(elem_type)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.tags.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IterMut<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let elem_type = self.elem_type;
+        self.tags
+            .next_back()
+            .map(|tag| TagMut::new(tag, Some(elem_type)))
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+/// A tag borrowed for modification, which keeps its type if it is an element of a list.
+///
+/// Compounds and lists inside the tag can be changed freely. The tag itself is
+/// replaced with `set`, which fails with `Error::ElementTypesDiffer` where that
+/// would mix tag types in a list.
+///
+/// ```
+/// use nbt_rust::NbtTag;
+///
+/// let mut player = NbtTag::from_snbt("{Pos:[0.5d,64.0d,-3.5d]}").unwrap();
+/// let mut y = player.get_mut("Pos").and_then(|pos| pos.get_mut(1)).unwrap();
+/// y.set(NbtTag::Double(70.0)).unwrap();
+/// assert!(y.set(NbtTag::Int(70)).is_err());
+/// assert_eq!(player["Pos"][1].as_f64(), Some(70.0));
+/// ```
+pub struct TagMut<'a> {
+    pub(crate) tag: &'a mut NbtTag,
+    /// Element type of the list holding the tag, if it is in one
+    elem_type: Option<TagType>,
+}
+
+impl<'a> TagMut<'a> {
+    pub(crate) fn new(tag: &'a mut NbtTag, elem_type: Option<TagType>) -> Self {
+        Self { tag, elem_type }
+    }
+
+    /// Replaces the tag, returning the old one
+    pub fn set(&mut self, tag: NbtTag) -> Result<NbtTag, Error> {
+        if self.elem_type.is_some_and(|t| t != tag.tag_type()) {
+            return Err(Error::ElementTypesDiffer);
+        }
+        Ok(mem::replace(self.tag, tag))
+    }
+
+    /// Returns the payload of a List tag for modification
+    pub fn as_list_mut(&mut self) -> Option<&mut NbtList> {
+        self.tag.as_list_mut()
+    }
+
+    /// Returns the payload of a Compound tag for modification
+    pub fn as_compound_mut(&mut self) -> Option<&mut NbtCompound> {
+        self.tag.as_compound_mut()
+    }
+
+    /// Like `as_list_mut`, but keeps the borrow for as long as this one
+    pub fn into_list_mut(self) -> Option<&'a mut NbtList> {
+        self.tag.as_list_mut()
+    }
+
+    /// Like `as_compound_mut`, but keeps the borrow for as long as this one
+    pub fn into_compound_mut(self) -> Option<&'a mut NbtCompound> {
+        self.tag.as_compound_mut()
+    }
+
+    /// Returns a tag of a compound by name, or an element of a list by position,
+    /// like `NbtTag::get_mut`. This consumes the borrow so that lookups can be chained
+    pub fn get_mut<I: TagIndex>(self, index: I) -> Option<TagMut<'a>> {
+        index.index_into_mut(self.tag)
+    }
+}
+
+impl Deref for TagMut<'_> {
+    type Target = NbtTag;
+
+    fn deref(&self) -> &NbtTag {
+        self.tag
+    }
+}
+
 impl NbtTag {
     /// Returns the tag prefix of the tag.
     pub const fn tag_type(&self) -> TagType {
@@ -85,82 +428,350 @@ impl NbtTag {
             NbtTag::LongArray(_) => TagType::LongArray,
         }
     }
+
+    /// Interprets a Byte tag as a flag, the way Minecraft does: any non-zero value is `true`.
+    /// Returns `None` for other tag types.
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            NbtTag::Byte(v) => Some(*v != 0),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a Byte tag
+    pub const fn as_i8(&self) -> Option<i8> {
+        match self {
+            NbtTag::Byte(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a Short tag
+    pub const fn as_i16(&self) -> Option<i16> {
+        match self {
+            NbtTag::Short(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an Int tag
+    pub const fn as_i32(&self) -> Option<i32> {
+        match self {
+            NbtTag::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a Long tag
+    pub const fn as_i64(&self) -> Option<i64> {
+        match self {
+            NbtTag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a Float tag
+    pub const fn as_f32(&self) -> Option<f32> {
+        match self {
+            NbtTag::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a Double tag
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            NbtTag::Double(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a String tag
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            NbtTag::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a ByteArray tag
+    pub fn as_byte_array(&self) -> Option<&[u8]> {
+        match self {
+            NbtTag::ByteArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of an IntArray tag
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            NbtTag::IntArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a LongArray tag
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            NbtTag::LongArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the payload of a List tag
+    pub const fn as_list(&self) -> Option<&NbtList> {
+        match self {
+            NbtTag::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the payload of a List tag for modification
+    pub fn as_list_mut(&mut self) -> Option<&mut NbtList> {
+        match self {
+            NbtTag::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the payload of a Compound tag
+    pub const fn as_compound(&self) -> Option<&NbtCompound> {
+        match self {
+            NbtTag::Compound(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the payload of a Compound tag for modification
+    pub fn as_compound_mut(&mut self) -> Option<&mut NbtCompound> {
+        match self {
+            NbtTag::Compound(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns a tag of a compound by name, or an element of a list by position.
+    /// Returns `None` if there is no such tag or this tag holds no other tags.
+    ///
+    /// ```
+    /// use nbt_rust::NbtTag;
+    ///
+    /// let level = NbtTag::from_snbt("{Data:{Player:{Pos:[0.5d,64.0d,-3.5d]}}}").unwrap();
+    /// let y = level.get("Data").and_then(|d| d.get("Player")).and_then(|p| p.get("Pos"));
+    /// assert_eq!(y.and_then(|pos| pos.get(1)).and_then(NbtTag::as_f64), Some(64.0));
+    /// assert_eq!(level["Data"]["Player"]["Pos"][1].as_f64(), Some(64.0));
+    /// ```
+    pub fn get<I: TagIndex>(&self, index: I) -> Option<&NbtTag> {
+        index.index_into(self)
+    }
+
+    /// Like `get`, but returns the tag for modification.
+    /// Elements of lists can only be replaced by tags of the list's element type
+    pub fn get_mut<I: TagIndex>(&mut self, index: I) -> Option<TagMut<'_>> {
+        index.index_into_mut(self)
+    }
+
+    /// Returns a tag of a compound converted to `T`, failing with an error
+    /// that names the missing key, or the expected and actual tag types
+    ///
+    /// ```
+    /// use nbt_rust::NbtTag;
+    ///
+    /// let item = NbtTag::from_snbt(r#"{id:"minecraft:stone",Count:64b}"#).unwrap();
+    /// let id: &str = item.try_get("id").unwrap();
+    /// let count: i8 = item.try_get("Count").unwrap();
+    ///
+    /// let error = item.try_get::<i32>("Count").unwrap_err();
+    /// assert_eq!(error.to_string(), "Expected a tag of type Int, found Byte at Count");
+    /// ```
+    pub fn try_get<'a, T>(&'a self, key: &str) -> Result<T, Error>
+    where
+        T: TryFrom<&'a NbtTag, Error = Error>,
+    {
+        let NbtTag::Compound(compound) = self else {
+            return Err(Error::TypeMismatch {
+                expected: TagType::Compound,
+                actual: self.tag_type(),
+            });
+        };
+
+        let tag = compound
+            .get(key)
+            .ok_or_else(|| Error::MissingKey(key.to_string()))?;
+        T::try_from(tag).map_err(|e| e.in_key(key))
+    }
 }
 
-impl fmt::Display for NbtTag {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+/// A type that can index into an `NbtTag`: a `&str` looks up a tag of a compound,
+/// a `usize` an element of a list
+pub trait TagIndex: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'a>(&self, tag: &'a NbtTag) -> Option<&'a NbtTag>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'a>(&self, tag: &'a mut NbtTag) -> Option<TagMut<'a>>;
+}
+
+impl TagIndex for str {
+    fn index_into<'a>(&self, tag: &'a NbtTag) -> Option<&'a NbtTag> {
+        match tag {
+            NbtTag::Compound(v) => v.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'a>(&self, tag: &'a mut NbtTag) -> Option<TagMut<'a>> {
+        match tag {
+            NbtTag::Compound(v) => v.get_mut(self).map(|tag| TagMut::new(tag, None)),
+            _ => None,
+        }
+    }
+}
+
+impl TagIndex for String {
+    fn index_into<'a>(&self, tag: &'a NbtTag) -> Option<&'a NbtTag> {
+        self.as_str().index_into(tag)
+    }
+
+    fn index_into_mut<'a>(&self, tag: &'a mut NbtTag) -> Option<TagMut<'a>> {
+        self.as_str().index_into_mut(tag)
+    }
+}
+
+impl TagIndex for usize {
+    fn index_into<'a>(&self, tag: &'a NbtTag) -> Option<&'a NbtTag> {
+        match tag {
+            NbtTag::List(v) => v.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'a>(&self, tag: &'a mut NbtTag) -> Option<TagMut<'a>> {
+        match tag {
+            NbtTag::List(v) => v.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl<T: TagIndex + ?Sized> TagIndex for &T {
+    fn index_into<'a>(&self, tag: &'a NbtTag) -> Option<&'a NbtTag> {
+        (**self).index_into(tag)
+    }
+
+    fn index_into_mut<'a>(&self, tag: &'a mut NbtTag) -> Option<TagMut<'a>> {
+        (**self).index_into_mut(tag)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl Sealed for usize {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+/// Looks up a tag like `get`.
+///
+/// # Panics
+///
+/// Panics if there is no such tag, like indexing a map or a slice does.
+impl<I: TagIndex> ops::Index<I> for NbtTag {
+    type Output = NbtTag;
+
+    fn index(&self, index: I) -> &NbtTag {
+        let tag_type = self.tag_type();
+        match index.index_into(self) {
+            Some(tag) => tag,
+            None => panic!("no such tag in a {tag_type:?} tag"),
+        }
+    }
+}
+
+/// Looks up a tag of a compound for modification. Elements of lists are not
+/// indexed mutably, since assigning to them could mix tag types: use `get_mut`.
+///
+/// # Panics
+///
+/// Panics if there is no such tag, like indexing a map does.
+impl ops::IndexMut<&str> for NbtTag {
+    fn index_mut(&mut self, key: &str) -> &mut NbtTag {
+        let tag_type = self.tag_type();
         match self {
-            NbtTag::End => write!(f, "End"),
-            NbtTag::Byte(v) => write!(f, "{v}b"),
-            NbtTag::Short(v) => write!(f, "{v}s"),
-            NbtTag::Int(v) => write!(f, "{v}"),
-            NbtTag::Long(v) => write!(f, "{v}l"),
-            NbtTag::Float(v) => write!(f, "{v}f"),
-            NbtTag::Double(v) => write!(f, "{v}d"),
-            NbtTag::String(v) => write!(f, "\"{v}\""),
-            NbtTag::ByteArray(v) => {
-                write!(f, "[B; ")?;
-                for (i, v) in v.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{v}")?;
-                }
-                write!(f, "]")
-            }
-            NbtTag::List(v) => {
-                write!(f, "[")?;
-                for (i, v) in v.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{v}")?;
-                }
-                write!(f, "]")
-            }
-            NbtTag::IntArray(v) => {
-                write!(f, "[I; ")?;
-                for (i, v) in v.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{v}")?;
-                }
-                write!(f, "]")
-            }
-            NbtTag::LongArray(v) => {
-                write!(f, "[L; ")?;
-                for (i, v) in v.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{v}")?;
-                }
-                write!(f, "]")
-            }
-            NbtTag::Compound(v) => {
-                write!(f, "{{")?;
-                for (i, (k, v)) in v.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
+            NbtTag::Compound(v) => v.get_mut(key),
+            _ => None,
+        }
+        .unwrap_or_else(|| panic!("no such tag in a {tag_type:?} tag"))
+    }
+}
+
+/// Fails with `Error::TypeMismatch` unless the tag is of the variant holding `T`
+macro_rules! try_from_tag {
+    ($($variant:ident => $ty:ty: |$v:ident| $convert:expr,)*) => {
+        $(
+            impl<'a> TryFrom<&'a NbtTag> for $ty {
+                type Error = Error;
+
+                fn try_from(tag: &'a NbtTag) -> Result<Self, Error> {
+                    match tag {
+                        NbtTag::$variant($v) => Ok($convert),
+                        _ => Err(Error::TypeMismatch {
+                            expected: TagType::$variant,
+                            actual: tag.tag_type(),
+                        }),
                     }
-                    write!(f, "\"{k}\": {v}")?;
                 }
-                write!(f, "}}")
             }
+        )*
+    };
+}
+
+try_from_tag! {
+    Byte => i8: |v| *v,
+    Byte => bool: |v| *v != 0,
+    Short => i16: |v| *v,
+    Int => i32: |v| *v,
+    Long => i64: |v| *v,
+    Float => f32: |v| *v,
+    Double => f64: |v| *v,
+    String => &'a str: |v| v,
+    String => String: |v| v.clone(),
+    ByteArray => &'a [u8]: |v| v,
+    IntArray => &'a [i32]: |v| v,
+    LongArray => &'a [i64]: |v| v,
+    List => &'a NbtList: |v| v,
+    Compound => &'a NbtCompound: |v| v,
+}
+
+/// Formats the tag as SNBT, on a single line exactly like `/data get` does.
+/// The alternate flag (`{:#}`) pretty-prints it over multiple indented lines.
+impl fmt::Display for NbtTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        if f.alternate() {
+            SnbtWriter::pretty().write(f, self)
+        } else {
+            SnbtWriter::compact().write(f, self)
         }
     }
 }
 
 impl From<u8> for NbtTag {
     fn from(v: u8) -> Self {
-        NbtTag::Byte(v)
+        NbtTag::Byte(v as i8)
     }
 }
 
 impl From<i8> for NbtTag {
     fn from(v: i8) -> Self {
-        NbtTag::Byte(v as u8)
+        NbtTag::Byte(v)
+    }
+}
+
+/// Minecraft stores flags as Byte tags holding 0 or 1
+impl From<bool> for NbtTag {
+    fn from(v: bool) -> Self {
+        NbtTag::Byte(v as i8)
     }
 }
 
@@ -224,21 +835,42 @@ impl From<String> for NbtTag {
     }
 }
 
-impl From<Vec<NbtTag>> for NbtTag {
-    fn from(v: Vec<NbtTag>) -> Self {
+impl From<&str> for NbtTag {
+    fn from(v: &str) -> Self {
+        NbtTag::String(v.to_string())
+    }
+}
+
+/// Fails like `NbtList::try_from` if the tags are of different types
+impl TryFrom<Vec<NbtTag>> for NbtTag {
+    type Error = Error;
+
+    fn try_from(v: Vec<NbtTag>) -> Result<Self, Error> {
+        NbtList::try_from(v).map(NbtTag::List)
+    }
+}
+
+impl From<NbtList> for NbtTag {
+    fn from(v: NbtList) -> Self {
         NbtTag::List(v)
     }
 }
 
 impl From<Vec<(String, NbtTag)>> for NbtTag {
     fn from(v: Vec<(String, NbtTag)>) -> Self {
-        let h: HashMap<String, NbtTag> = v.into_iter().collect();
-        NbtTag::Compound(h)
+        NbtTag::Compound(v.into_iter().collect())
     }
 }
 
 impl From<HashMap<String, NbtTag>> for NbtTag {
     fn from(v: HashMap<String, NbtTag>) -> Self {
+        NbtTag::Compound(v.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl From<NbtCompound> for NbtTag {
+    fn from(v: NbtCompound) -> Self {
         NbtTag::Compound(v)
     }
 }
@@ -260,3 +892,104 @@ impl From<()> for NbtTag {
         NbtTag::End
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(values: &[i32]) -> Vec<NbtTag> {
+        values.iter().copied().map(NbtTag::Int).collect()
+    }
+
+    #[test]
+    fn try_from_rejects_mixed_tags() {
+        let list = NbtList::try_from(ints(&[1, 2])).unwrap();
+        assert_eq!(list.elem_type(), TagType::Int);
+        assert_eq!(
+            NbtList::try_from(Vec::new()).unwrap().elem_type(),
+            TagType::End
+        );
+
+        let mixed = vec![NbtTag::Int(1), NbtTag::String("a".to_string())];
+        let error = NbtList::try_from(mixed.clone()).err().unwrap();
+        assert!(matches!(error, Error::ElementTypesDiffer));
+        assert!(matches!(
+            NbtTag::try_from(mixed),
+            Err(Error::ElementTypesDiffer)
+        ));
+    }
+
+    #[test]
+    fn set_keeps_the_element_type() {
+        let mut list = NbtList::try_from(ints(&[1, 2])).unwrap();
+        assert!(list.set(1, NbtTag::Int(3)).unwrap() == NbtTag::Int(2));
+        assert!(matches!(
+            list.set(0, NbtTag::Long(3)),
+            Err(Error::ElementTypesDiffer)
+        ));
+        assert!(list.into_inner() == ints(&[1, 3]));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn set_panics_out_of_bounds() {
+        let _ = NbtList::new(TagType::Int).set(0, NbtTag::Int(1));
+    }
+
+    #[test]
+    fn elements_are_modified_through_tag_mut() {
+        let mut list = NbtList::try_from(ints(&[1, 2, 3])).unwrap();
+        for mut tag in list.iter_mut().rev().skip(1) {
+            let doubled = tag.as_i32().unwrap() * 2;
+            tag.set(NbtTag::Int(doubled)).unwrap();
+            assert!(tag.set(NbtTag::Byte(0)).is_err());
+        }
+        assert!(list.get_mut(3).is_none());
+        assert!(list.into_inner() == ints(&[2, 4, 3]));
+
+        let mut tag = NbtTag::from_snbt("{a:[{b:1}],c:2}").unwrap();
+        let mut b = tag
+            .get_mut("a")
+            .and_then(|a| a.get_mut(0))
+            .and_then(|elem| elem.get_mut("b"))
+            .unwrap();
+        // Tags of compounds are not restricted, even inside a list
+        b.set(NbtTag::String("one".to_string())).unwrap();
+        assert!(tag.get_mut("c").unwrap().set(NbtTag::End).is_ok());
+        tag["c"] = NbtTag::Byte(2);
+        assert_eq!(tag["a"][0]["b"].as_str(), Some("one"));
+        assert!(tag["c"] == NbtTag::Byte(2));
+    }
+
+    #[test]
+    fn lists_never_hold_end_tags() {
+        let mut list = NbtList::new_empty();
+        assert!(matches!(
+            list.push(NbtTag::End),
+            Err(Error::ElementTypesDiffer)
+        ));
+        assert!(matches!(
+            list.insert(0, NbtTag::End),
+            Err(Error::ElementTypesDiffer)
+        ));
+        assert!(list.is_empty());
+        assert_eq!(list.elem_type(), TagType::End);
+
+        let error = NbtList::with_tags(TagType::End, vec![NbtTag::End])
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::ElementTypesDiffer));
+        assert!(NbtList::try_from(vec![NbtTag::End]).is_err());
+        assert!(NbtList::with_tags(TagType::End, Vec::new()).is_ok());
+
+        list.push(NbtTag::Int(1)).unwrap();
+        assert!(list.push(NbtTag::End).is_err());
+        assert!(list.get_mut(0).unwrap().set(NbtTag::End).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "element 1 is an End tag")]
+    fn macro_lists_never_hold_end_tags() {
+        NbtList::from_macro_elements(vec![NbtTag::Int(1), NbtTag::End]);
+    }
+}
@@ -0,0 +1,1158 @@
+use std::fmt;
+
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+    Deserialize, Deserializer,
+};
+
+use super::{NbtCompound, NbtList, NbtTag, TagType};
+use crate::{
+    error::{Error, Result},
+    ser::serde_impl::MapKeySerializer,
+    serde_with::{
+        array_name, empty_list_name, empty_list_type, forced_type, ArrayEntry, ElementSerializer,
+        INT_ARRAY, LONG_ARRAY, TAG,
+    },
+};
+
+/// Converts any `serde::Serialize` value into an `NbtTag`
+///
+/// The value is laid out like [`to_bytes`](crate::to_bytes) writes it: structs
+/// and maps become Compound tags, sequences become List tags, `None` fields
+/// are left out and the `byte_array`, `int_array`, `long_array` and `list`
+/// helpers select the tag type of a field.
+///
+/// ```
+/// use nbt_rust::NbtTag;
+///
+/// #[derive(serde::Serialize)]
+/// struct Item {
+///     id: String,
+///     #[serde(rename = "Count")]
+///     count: i8,
+/// }
+///
+/// let tag = nbt_rust::to_value(&Item { id: "minecraft:stone".into(), count: 64 }).unwrap();
+/// assert!(tag["Count"] == NbtTag::Byte(64));
+/// ```
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<NbtTag> {
+    value.serialize(TagSerializer)
+}
+
+/// Deserializes a `serde::Deserialize` value from an `NbtTag`
+///
+/// Since the tag is borrowed, a subtree of a larger tag can be deserialized
+/// without taking it apart first. Owned tags can also be used as a deserializer
+/// directly through `IntoDeserializer`.
+///
+/// ```
+/// use nbt_rust::NbtTag;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Pos {
+///     x: i32,
+///     z: i32,
+/// }
+///
+/// let chunk = NbtTag::from_snbt("{Level:{Pos:{x:3,z:-7},Sections:[]}}").unwrap();
+/// let pos: Pos = nbt_rust::from_value(&chunk["Level"]["Pos"]).unwrap();
+/// assert_eq!((pos.x, pos.z), (3, -7));
+/// ```
+pub fn from_value<T: DeserializeOwned>(tag: &NbtTag) -> Result<T> {
+    T::deserialize(tag)
+}
+
+/// Serializes the tag with its exact type, so writing it with
+/// [`to_bytes`](crate::to_bytes) keeps array tags apart from List tags,
+/// and empty lists keep their element type.
+impl Serialize for NbtTag {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use ser::{SerializeMap, SerializeSeq};
+
+        match self {
+            NbtTag::End => serializer.serialize_unit(),
+            NbtTag::Byte(v) => serializer.serialize_i8(*v),
+            NbtTag::Short(v) => serializer.serialize_i16(*v),
+            NbtTag::Int(v) => serializer.serialize_i32(*v),
+            NbtTag::Long(v) => serializer.serialize_i64(*v),
+            NbtTag::Float(v) => serializer.serialize_f32(*v),
+            NbtTag::Double(v) => serializer.serialize_f64(*v),
+            NbtTag::ByteArray(v) => serializer.serialize_bytes(v),
+            NbtTag::String(v) => serializer.serialize_str(v),
+            NbtTag::List(list) if list.is_empty() && list.elem_type() != TagType::End => {
+                let name = empty_list_name(list.elem_type());
+                serializer.serialize_newtype_struct(name, &[] as &[NbtTag])
+            }
+            NbtTag::List(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for tag in list {
+                    seq.serialize_element(tag)?;
+                }
+                seq.end()
+            }
+            NbtTag::Compound(compound) => {
+                let mut map = serializer.serialize_map(Some(compound.len()))?;
+                for (key, tag) in compound {
+                    map.serialize_entry(key, tag)?;
+                }
+                map.end()
+            }
+            NbtTag::IntArray(v) => serializer.serialize_newtype_struct(INT_ARRAY, v),
+            NbtTag::LongArray(v) => serializer.serialize_newtype_struct(LONG_ARRAY, v),
+        }
+    }
+}
+
+/// Deserializes any value into the closest tag type.
+///
+/// The deserializers of this crate keep array tags apart from List tags;
+/// with other formats, every sequence becomes a List.
+impl<'de> Deserialize<'de> for NbtTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(TAG, TagVisitor)
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = NbtTag;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any NBT tag")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i8<E: de::Error>(self, v: i8) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i16<E: de::Error>(self, v: i16) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i32<E: de::Error>(self, v: i32) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u8<E: de::Error>(self, v: u8) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u16<E: de::Error>(self, v: u16) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u32<E: de::Error>(self, v: u32) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<NbtTag, E> {
+        Ok(v.into())
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<NbtTag, E> {
+        Ok(NbtTag::ByteArray(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<NbtTag, E> {
+        Ok(NbtTag::ByteArray(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<NbtTag, E> {
+        Ok(NbtTag::End)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<NbtTag, D::Error> {
+        NbtTag::deserialize(deserializer)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<NbtTag, E> {
+        Ok(NbtTag::End)
+    }
+
+    // Other formats pass the newtype struct of `NbtTag::deserialize` through
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<NbtTag, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<NbtTag, A::Error> {
+        let mut list = NbtList::new_empty();
+        while let Some(tag) = seq.next_element::<NbtTag>()? {
+            if tag == NbtTag::End {
+                return Err(de::Error::custom("None or unit inside a list"));
+            }
+            list.push(tag).map_err(de::Error::custom)?;
+        }
+        Ok(NbtTag::List(list))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<NbtTag, A::Error> {
+        let mut compound = NbtCompound::new();
+        while let Some(key) = map.next_key::<String>()? {
+            // An array tag or an empty typed list handed over by `ArrayEntry`
+            if compound.is_empty() {
+                if let Some(elem_type) = empty_list_type(&key) {
+                    map.next_value::<de::IgnoredAny>()?;
+                    return Ok(NbtTag::List(NbtList::new(elem_type)));
+                }
+                match forced_type(&key) {
+                    Some(TagType::ByteArray) => return Ok(NbtTag::ByteArray(map.next_value()?)),
+                    Some(TagType::IntArray) => return Ok(NbtTag::IntArray(map.next_value()?)),
+                    Some(TagType::LongArray) => return Ok(NbtTag::LongArray(map.next_value()?)),
+                    _ => {}
+                }
+            }
+
+            let tag: NbtTag = map.next_value()?;
+            if tag != NbtTag::End {
+                compound.insert(key, tag);
+            }
+        }
+        Ok(NbtTag::Compound(compound))
+    }
+}
+
+/// Serializes values into an `NbtTag`; `None` and unit become End tags,
+/// which compounds leave out
+struct TagSerializer;
+
+impl ser::Serializer for TagSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = ListSerializer;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = CompoundSerializer;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<NbtTag> {
+        Ok(NbtTag::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<NbtTag> {
+        Ok(v.into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<NbtTag> {
+        Ok(NbtTag::ByteArray(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<NbtTag> {
+        Ok(NbtTag::End)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<NbtTag> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<NbtTag> {
+        Ok(NbtTag::End)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<NbtTag> {
+        Ok(NbtTag::Compound(NbtCompound::new()))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<NbtTag> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<NbtTag> {
+        if let Some(elem_type) = empty_list_type(name) {
+            return Ok(NbtTag::List(NbtList::new(elem_type)));
+        }
+        let tag = value.serialize(self)?;
+        match forced_type(name) {
+            Some(tag_type) => force(tag, tag_type),
+            None => Ok(tag),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<NbtTag> {
+        let tag = value.serialize(self).map_err(|e| e.in_key(variant))?;
+        Ok(in_variant(tag, Some(variant)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer> {
+        Ok(ListSerializer {
+            tags: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer> {
+        let mut list = self.serialize_seq(Some(len))?;
+        list.variant = Some(variant);
+        Ok(list)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<CompoundSerializer> {
+        Ok(CompoundSerializer {
+            compound: NbtCompound::new(),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<CompoundSerializer> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<CompoundSerializer> {
+        let mut compound = self.serialize_map(Some(len))?;
+        compound.variant = Some(variant);
+        Ok(compound)
+    }
+}
+
+/// Converts a serialized sequence into the tag type selected by one of the `serde_with` helpers
+fn force(tag: NbtTag, tag_type: TagType) -> Result<NbtTag> {
+    let list = match tag {
+        NbtTag::End => return Ok(NbtTag::End),
+        NbtTag::List(list) if tag_type == TagType::List => return Ok(NbtTag::List(list)),
+        NbtTag::ByteArray(v) if tag_type == TagType::ByteArray => return Ok(NbtTag::ByteArray(v)),
+        NbtTag::ByteArray(v) if tag_type == TagType::List => {
+            let bytes = v.into_iter().map(NbtTag::from).collect();
+            return Ok(NbtTag::List(NbtList::with_tags(TagType::Byte, bytes)?));
+        }
+        NbtTag::List(list) => list,
+        tag => {
+            return Err(Error::TypeMismatch {
+                expected: tag_type,
+                actual: tag.tag_type(),
+            })
+        }
+    };
+
+    // The element serializer applies the same range checks as when writing bytes
+    let elems = list
+        .iter()
+        .enumerate()
+        .map(|(index, tag)| {
+            tag.serialize(ElementSerializer { array: tag_type })
+                .map_err(|e| e.in_index(index))
+        })
+        .collect::<Result<Vec<i64>>>()?;
+    Ok(match tag_type {
+        TagType::ByteArray => NbtTag::ByteArray(elems.into_iter().map(|v| v as u8).collect()),
+        TagType::IntArray => NbtTag::IntArray(elems.into_iter().map(|v| v as i32).collect()),
+        _ => NbtTag::LongArray(elems),
+    })
+}
+
+/// Wraps a tag in a compound named after the enum variant it belongs to, if any
+fn in_variant(tag: NbtTag, variant: Option<&str>) -> NbtTag {
+    match variant {
+        Some(variant) => {
+            let mut compound = NbtCompound::new();
+            compound.insert(variant.to_string(), tag);
+            NbtTag::Compound(compound)
+        }
+        None => tag,
+    }
+}
+
+/// Records the enum variant wrapping a list or struct in the path of an error
+fn error_in_variant(error: Error, variant: Option<&str>) -> Error {
+    match variant {
+        Some(variant) => error.in_key(variant),
+        None => error,
+    }
+}
+
+/// Collects sequences and tuples into a List tag
+struct ListSerializer {
+    tags: Vec<NbtTag>,
+    /// Name of the enum variant whose compound wraps the list, if any
+    variant: Option<&'static str>,
+}
+
+impl ListSerializer {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let index = self.tags.len();
+        let tag = value
+            .serialize(TagSerializer)
+            .and_then(|tag| match tag {
+                NbtTag::End => Err(Error::Unsupported("None or unit inside a list")),
+                tag => Ok(tag),
+            })
+            .map_err(|e| error_in_variant(e.in_index(index), self.variant))?;
+
+        if self
+            .tags
+            .first()
+            .is_some_and(|first| first.tag_type() != tag.tag_type())
+        {
+            return Err(error_in_variant(
+                Error::ElementTypesDiffer.in_index(index),
+                self.variant,
+            ));
+        }
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<NbtTag> {
+        let list = NbtList::try_from(self.tags)?;
+        Ok(in_variant(NbtTag::List(list), self.variant))
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for ListSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+/// Collects structs and maps into a Compound tag
+struct CompoundSerializer {
+    compound: NbtCompound,
+    key: Option<String>,
+    /// Name of the enum variant whose compound wraps the struct, if any
+    variant: Option<&'static str>,
+}
+
+impl CompoundSerializer {
+    fn field<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<()> {
+        match value.serialize(TagSerializer) {
+            Ok(NbtTag::End) => Ok(()),
+            Ok(tag) => {
+                self.compound.insert(key, tag);
+                Ok(())
+            }
+            Err(e) => Err(error_in_variant(e.in_key(&key), self.variant)),
+        }
+    }
+
+    fn finish(self) -> Result<NbtTag> {
+        Ok(in_variant(NbtTag::Compound(self.compound), self.variant))
+    }
+}
+
+impl ser::SerializeMap for CompoundSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().ok_or_else(|| {
+            Error::Message("serialize_value called before serialize_key".to_string())
+        })?;
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for CompoundSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for CompoundSerializer {
+    type Ok = NbtTag;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<NbtTag> {
+        self.finish()
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for NbtTag {
+    type Deserializer = NbtTag;
+
+    fn into_deserializer(self) -> NbtTag {
+        self
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, Error> for &'a NbtTag {
+    type Deserializer = &'a NbtTag;
+
+    fn into_deserializer(self) -> &'a NbtTag {
+        self
+    }
+}
+
+/// Deserializes values from a tag the way the binary deserializer reads them from bytes
+impl<'de> de::Deserializer<'de> for &NbtTag {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::End => visitor.visit_unit(),
+            NbtTag::Byte(v) => visitor.visit_i8(*v),
+            NbtTag::Short(v) => visitor.visit_i16(*v),
+            NbtTag::Int(v) => visitor.visit_i32(*v),
+            NbtTag::Long(v) => visitor.visit_i64(*v),
+            NbtTag::Float(v) => visitor.visit_f32(*v),
+            NbtTag::Double(v) => visitor.visit_f64(*v),
+            NbtTag::String(v) => visitor.visit_str(v),
+            NbtTag::ByteArray(v) => {
+                visitor.visit_seq(SeqAccess::new(v.iter().map(|&b| NbtTag::Byte(b as i8))))
+            }
+            NbtTag::IntArray(v) => {
+                visitor.visit_seq(SeqAccess::new(v.iter().map(|&i| NbtTag::Int(i))))
+            }
+            NbtTag::LongArray(v) => {
+                visitor.visit_seq(SeqAccess::new(v.iter().map(|&l| NbtTag::Long(l))))
+            }
+            NbtTag::List(list) => visitor.visit_seq(SeqAccess::new(list.iter())),
+            NbtTag::Compound(compound) => visitor.visit_map(MapAccess {
+                iter: compound.iter(),
+                pending: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::Byte(v) => visitor.visit_bool(*v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    // Unsigned integers are stored as their two's complement bit pattern,
+    // so they are read back the same way instead of being range checked
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::Byte(v) => visitor.visit_u8(*v as u8),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::Short(v) => visitor.visit_u16(*v as u16),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::Int(v) => visitor.visit_u32(*v as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::Long(v) => visitor.visit_u64(*v as u64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::ByteArray(v) => visitor.visit_bytes(v),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::End => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if name == TAG {
+            return match self {
+                NbtTag::List(list) if list.is_empty() && list.elem_type() != TagType::End => {
+                    let name = empty_list_name(list.elem_type());
+                    visitor.visit_map(ArrayEntry::new(name, ().into_deserializer()))
+                }
+                _ => match array_name(self.tag_type()) {
+                    Some(array) => visitor.visit_map(ArrayEntry::new(array, self)),
+                    None => self.deserialize_any(visitor),
+                },
+            };
+        }
+        match forced_type(name) {
+            Some(expected) if expected != self.tag_type() => Err(Error::TypeMismatch {
+                expected,
+                actual: self.tag_type(),
+            }),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            // Unit variants are stored as their name
+            NbtTag::String(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            // Other variants are a compound with a single tag named after the variant
+            NbtTag::Compound(compound) => {
+                let mut iter = compound.iter();
+                match (iter.next(), iter.next()) {
+                    (Some((variant, value)), None) => {
+                        visitor.visit_enum(EnumAccess { variant, value })
+                    }
+                    (None, _) => Err(de::Error::custom(
+                        "expected an enum variant, found an empty compound",
+                    )),
+                    (Some(_), Some(_)) => Err(de::Error::custom(
+                        "expected a single tag in an enum compound",
+                    )),
+                }
+            }
+            _ => Err(de::Error::custom(
+                "expected a String or a Compound tag for an enum",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Deserializes values from an owned tag, like a borrowed one
+impl<'de> de::Deserializer<'de> for NbtTag {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_any(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_bool(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_u8(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_u16(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_u32(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_u64(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::String(v) => visitor.visit_string(v),
+            tag => (&tag).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            NbtTag::ByteArray(v) => visitor.visit_byte_buf(v),
+            tag => (&tag).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        (&self).deserialize_option(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self).deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Deserializes the elements of a List tag or of an array tag
+struct SeqAccess<I> {
+    iter: I,
+    index: usize,
+}
+
+impl<I: ExactSizeIterator> SeqAccess<I> {
+    fn new(iter: I) -> Self {
+        Self { iter, index: 0 }
+    }
+}
+
+impl<'de, I> de::SeqAccess<'de> for SeqAccess<I>
+where
+    I: ExactSizeIterator,
+    I::Item: IntoDeserializer<'de, Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        let Some(tag) = self.iter.next() else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+
+        seed.deserialize(tag.into_deserializer())
+            .map(Some)
+            .map_err(|e| e.in_index(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Deserializes the named tags of a Compound tag
+struct MapAccess<'a, I> {
+    iter: I,
+    /// The tag whose name was just deserialized as a key, and that name
+    pending: Option<(&'a str, &'a NbtTag)>,
+}
+
+impl<'de, 'a, I> de::MapAccess<'de> for MapAccess<'a, I>
+where
+    I: ExactSizeIterator<Item = (&'a String, &'a NbtTag)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let Some((key, tag)) = self.iter.next() else {
+            return Ok(None);
+        };
+        self.pending = Some((key, tag));
+        seed.deserialize(key.as_str().into_deserializer())
+            .map(Some)
+            .map_err(|e: Error| e.in_key(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, tag) = self
+            .pending
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("next_value called before next_key"))?;
+        seed.deserialize(tag).map_err(|e| e.in_key(key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Deserializes an enum stored as a compound with a single tag named after the variant
+struct EnumAccess<'a> {
+    variant: &'a str,
+    value: &'a NbtTag,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = Error;
+    type Variant = VariantAccess<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed
+            .deserialize(self.variant.into_deserializer())
+            .map_err(|e: Error| e.in_key(self.variant))?;
+        Ok((
+            variant,
+            VariantAccess {
+                variant: self.variant,
+                value: self.value,
+            },
+        ))
+    }
+}
+
+struct VariantAccess<'a> {
+    variant: &'a str,
+    value: &'a NbtTag,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.value)
+            .map_err(|e| e.in_key(self.variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.value, visitor).map_err(|e| e.in_key(self.variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.value, visitor).map_err(|e| e.in_key(self.variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de::from_bytes as read_tag, from_bytes, ser::to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    /// Empty lists of every element type, at the root of a field and inside another list
+    fn empty_lists() -> NbtTag {
+        let mut compound = NbtCompound::new();
+        compound.insert("entities".into(), NbtList::new(TagType::Compound).into());
+        compound.insert("names".into(), NbtList::new(TagType::String).into());
+        compound.insert("none".into(), NbtList::new(TagType::End).into());
+        let inner = NbtList::new(TagType::IntArray).into();
+        let outer = NbtList::with_tags(TagType::List, vec![inner]).unwrap();
+        compound.insert("nested".into(), outer.into());
+        NbtTag::Compound(compound)
+    }
+
+    fn elem_type(tag: &NbtTag) -> TagType {
+        tag.as_list().unwrap().elem_type()
+    }
+
+    #[test]
+    fn empty_lists_keep_their_element_type_through_values() {
+        let tag = empty_lists();
+        let value = to_value(&tag).unwrap();
+        assert_eq!(elem_type(&value["entities"]), TagType::Compound);
+        assert!(value == tag);
+        assert!(from_value::<NbtTag>(&tag).unwrap() == tag);
+    }
+
+    #[test]
+    fn empty_lists_keep_their_element_type_through_bytes() {
+        let tag = empty_lists();
+        let bytes = to_bytes("", &tag).unwrap();
+        let (_, read) = read_tag(&bytes).unwrap();
+        assert_eq!(elem_type(&read["names"]), TagType::String);
+        assert_eq!(elem_type(&read["nested"][0]), TagType::IntArray);
+        assert!(read == tag);
+        assert!(from_bytes::<NbtTag>(&bytes).unwrap() == tag);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Item {
+        id: String,
+        #[serde(rename = "Count")]
+        count: u8,
+        damage: Option<i16>,
+        enchantments: Vec<Enchantment>,
+        mode: Mode,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Enchantment {
+        id: String,
+        lvl: i16,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Mode {
+        Survival,
+        Spawner { delay: i32 },
+    }
+
+    fn item() -> Item {
+        Item {
+            id: "minecraft:diamond_sword".into(),
+            count: 200,
+            damage: None,
+            enchantments: vec![Enchantment {
+                id: "minecraft:sharpness".into(),
+                lvl: 5,
+            }],
+            mode: Mode::Survival,
+        }
+    }
+
+    #[test]
+    fn structs_round_trip_through_values() {
+        let tag = to_value(&item()).unwrap();
+        // Unsigned integers keep their bit pattern
+        assert!(tag["Count"] == NbtTag::Byte(200u8 as i8));
+        assert!(tag.get("damage").is_none());
+        assert!(tag["enchantments"][0]["lvl"] == NbtTag::Short(5));
+        assert!(tag["mode"] == NbtTag::String("Survival".into()));
+        assert_eq!(from_value::<Item>(&tag).unwrap(), item());
+
+        let spawner = Item {
+            damage: Some(3),
+            mode: Mode::Spawner { delay: 20 },
+            ..item()
+        };
+        let tag = to_value(&spawner).unwrap();
+        assert!(tag["damage"] == NbtTag::Short(3));
+        assert!(tag["mode"]["Spawner"]["delay"] == NbtTag::Int(20));
+        assert_eq!(from_value::<Item>(&tag).unwrap(), spawner);
+    }
+
+    #[test]
+    fn owned_tags_are_deserializers() {
+        let tag = to_value(&item()).unwrap();
+        assert_eq!(Item::deserialize(tag.clone()).unwrap(), item());
+        assert_eq!(Item::deserialize(tag.into_deserializer()).unwrap(), item());
+    }
+
+    #[test]
+    fn subtrees_deserialize_in_place() {
+        let tag = NbtTag::from_snbt("{Inventory:[{id:\"minecraft:bow\",lvl:1s}]}").unwrap();
+        let first: Enchantment = from_value(&tag["Inventory"][0]).unwrap();
+        assert_eq!(first.id, "minecraft:bow");
+        assert_eq!(first.lvl, 1);
+    }
+
+    #[test]
+    fn to_value_errors_name_the_tag() {
+        let error = to_value(&vec![Some(1), None]).err().unwrap();
+        assert_eq!(error.path(), Some("[1]"));
+
+        let mut map = std::collections::HashMap::new();
+        map.insert(1, "one");
+        let error = to_value(&map).err().unwrap();
+        assert!(matches!(error.inner(), Error::KeyMustBeString));
+    }
+
+    #[test]
+    fn from_value_errors_name_the_tag() {
+        let tag = NbtTag::from_snbt(
+            "{id:\"minecraft:bow\",Count:1b,enchantments:[{id:\"minecraft:power\",lvl:\"five\"}],mode:\"Survival\"}",
+        )
+        .unwrap();
+        let error = from_value::<Item>(&tag).err().unwrap();
+        assert_eq!(error.path(), Some("enchantments[0].lvl"));
+
+        let tag = NbtTag::from_snbt("{Count:1b,enchantments:[],mode:\"Survival\"}").unwrap();
+        let error = from_value::<Item>(&tag).err().unwrap();
+        assert!(error.to_string().contains("missing field `id`"), "{error}");
+    }
+}
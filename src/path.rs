@@ -0,0 +1,879 @@
+//! NBT paths, the language Minecraft's `/data` command uses to select tags
+//!
+//! A path is a sequence of nodes separated by dots:
+//!
+//! - `name` or `"quoted name"` selects a tag of a compound
+//! - `name{Count:64b}` selects it only if it matches the compound pattern
+//! - `{Count:64b}` at the start selects the root tag if it matches the pattern
+//! - `[2]` selects an element of a list, `[-1]` counting from the end
+//! - `[]` selects all elements of a list
+//! - `[{Slot:0b}]` selects the elements of a list matching the pattern
+//!
+//! A pattern matches a tag if every tag in the pattern has an equal tag in the
+//! matched compound. Lists in a pattern match lists containing all of their
+//! elements, and an empty list in a pattern only matches an empty list.
+//!
+//! Elements of ByteArray, IntArray and LongArray tags are plain numbers rather
+//! than tags in this crate, so indices only select elements of lists.
+//!
+//! ```
+//! use nbt_rust::{path::NbtPath, NbtTag};
+//!
+//! let player = NbtTag::from_snbt(
+//!     r#"{Inventory:[{Slot:0b,id:"minecraft:stone",Count:64b},{Slot:1b,id:"minecraft:dirt",Count:3b}]}"#,
+//! ).unwrap();
+//!
+//! let path = NbtPath::parse("Inventory[{Slot:1b}].id").unwrap();
+//! assert_eq!(player.query(&path)[0].as_str(), Some("minecraft:dirt"));
+//!
+//! let counts = player.query(&"Inventory[].Count".parse().unwrap()).len();
+//! assert_eq!(counts, 2);
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    error::{Error, Result},
+    nbt_tag::{NbtCompound, NbtList, TagMut, TagType},
+    snbt::Parser,
+    NbtTag,
+};
+
+/// A parsed NBT path, such as `Inventory[{Slot:0b}].tag.display.Name`
+#[derive(Clone, PartialEq)]
+pub struct NbtPath {
+    nodes: Vec<Node>,
+    source: String,
+}
+
+/// A single step of a path
+#[derive(Clone, PartialEq)]
+pub(crate) enum Node {
+    /// `name`: a tag of a compound
+    Key(String),
+    /// `name{...}`: a tag of a compound that matches the pattern
+    MatchKey(String, NbtTag),
+    /// `{...}` at the start of the path: the root tag, if it matches the pattern
+    MatchRoot(NbtTag),
+    /// `[i]`: an element of a list, counted from the end if negative
+    Index(i32),
+    /// `[]`: every element of a list
+    AllElements,
+    /// `[{...}]`: the elements of a list that match the pattern
+    MatchElements(NbtTag),
+}
+
+impl NbtPath {
+    /// Parses a path, reporting the position of any syntax error
+    pub fn parse(s: &str) -> Result<NbtPath> {
+        let mut parser = Parser { input: s, pos: 0 };
+
+        let mut nodes = Vec::new();
+        loop {
+            nodes.push(parse_node(&mut parser, nodes.is_empty())?);
+
+            match parser.peek() {
+                None => break,
+                Some('[' | '{') => {}
+                Some(_) => parser.expect('.')?,
+            }
+        }
+
+        Ok(NbtPath {
+            nodes,
+            source: s.to_string(),
+        })
+    }
+}
+
+fn parse_node(parser: &mut Parser, first: bool) -> Result<Node> {
+    match parser.peek() {
+        Some('{') if first => Ok(Node::MatchRoot(parser.parse_compound()?)),
+        Some('[') => {
+            parser.bump();
+            let node = match parser.peek() {
+                Some('{') => Node::MatchElements(parser.parse_compound()?),
+                Some(']') => Node::AllElements,
+                _ => Node::Index(parse_index(parser)?),
+            };
+            parser.expect(']')?;
+            Ok(node)
+        }
+        Some('"' | '\'') => {
+            let key = parser.parse_quoted()?;
+            parse_key_node(parser, key)
+        }
+        Some(c) if is_key_char(c) => {
+            let start = parser.pos;
+            while parser.peek().is_some_and(is_key_char) {
+                parser.bump();
+            }
+            let key = parser.input[start..parser.pos].to_string();
+            parse_key_node(parser, key)
+        }
+        _ => Err(parser.error_here("Invalid NBT path element")),
+    }
+}
+
+/// A key, optionally followed by a pattern its tag has to match
+fn parse_key_node(parser: &mut Parser, key: String) -> Result<Node> {
+    match parser.peek() {
+        Some('{') => Ok(Node::MatchKey(key, parser.parse_compound()?)),
+        _ => Ok(Node::Key(key)),
+    }
+}
+
+fn parse_index(parser: &mut Parser) -> Result<i32> {
+    let start = parser.pos;
+    if parser.peek() == Some('-') {
+        parser.bump();
+    }
+    while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+        parser.bump();
+    }
+
+    let digits = &parser.input[start..parser.pos];
+    digits
+        .parse()
+        .map_err(|_| parser.error(start..parser.pos.max(start + 1), "Expected an index"))
+}
+
+/// Characters that may appear in unquoted keys of a path
+fn is_key_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '"' | '\'' | '[' | ']' | '.' | '{' | '}')
+}
+
+impl FromStr for NbtPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        NbtPath::parse(s)
+    }
+}
+
+/// Prints the path as it was parsed
+impl fmt::Display for NbtPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl fmt::Debug for NbtPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NbtPath({:?})", self.source)
+    }
+}
+
+impl Node {
+    /// Collects the tags this node selects from `tag`
+    pub(crate) fn select<'a>(&self, tag: &'a NbtTag, out: &mut Vec<&'a NbtTag>) {
+        match (self, tag) {
+            (Node::Key(key), NbtTag::Compound(c)) => out.extend(c.get(key)),
+            (Node::MatchKey(key, pattern), NbtTag::Compound(c)) => {
+                out.extend(c.get(key).filter(|t| matches(pattern, t)))
+            }
+            (Node::MatchRoot(pattern), tag) if matches(pattern, tag) => out.push(tag),
+            (Node::Index(index), NbtTag::List(list)) => {
+                out.extend(resolve_index(*index, list.len()).map(|i| &list[i]))
+            }
+            (Node::AllElements, NbtTag::List(list)) => out.extend(list.iter()),
+            (Node::MatchElements(pattern), NbtTag::List(list)) => {
+                out.extend(list.iter().filter(|t| matches(pattern, t)))
+            }
+            _ => {}
+        }
+    }
+
+    /// Collects the tags this node selects from `tag` for modification
+    pub(crate) fn select_mut<'a>(&self, tag: TagMut<'a>, out: &mut Vec<TagMut<'a>>) {
+        if let Node::MatchRoot(pattern) = self {
+            if matches(pattern, &tag) {
+                out.push(tag);
+            }
+            return;
+        }
+        match (self, tag.tag) {
+            (Node::Key(key), NbtTag::Compound(c)) => {
+                out.extend(c.get_mut(key).map(|t| TagMut::new(t, None)))
+            }
+            (Node::MatchKey(key, pattern), NbtTag::Compound(c)) => out.extend(
+                c.get_mut(key)
+                    .filter(|t| matches(pattern, t))
+                    .map(|t| TagMut::new(t, None)),
+            ),
+            (Node::Index(index), NbtTag::List(list)) => {
+                if let Some(i) = resolve_index(*index, list.len()) {
+                    out.extend(list.get_mut(i));
+                }
+            }
+            (Node::AllElements, NbtTag::List(list)) => out.extend(list.iter_mut()),
+            (Node::MatchElements(pattern), NbtTag::List(list)) => {
+                out.extend(list.iter_mut().filter(|t| matches(pattern, t)))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Node methods backing the `/data modify` operations
+impl Node {
+    /// The tag a missing parent of this node is created as
+    fn preferred_parent(&self) -> NbtTag {
+        match self {
+            Node::Key(_) | Node::MatchKey(..) | Node::MatchRoot(_) => {
+                NbtTag::Compound(NbtCompound::new())
+            }
+            Node::Index(_) | Node::AllElements | Node::MatchElements(_) => {
+                NbtTag::List(NbtList::new_empty())
+            }
+        }
+    }
+
+    /// Like `select_mut`, but creates the selected tag from `default` (or the
+    /// pattern) when it is missing, the way the game does
+    fn select_or_create<'a>(
+        &self,
+        tag: TagMut<'a>,
+        default: &NbtTag,
+        out: &mut Vec<TagMut<'a>>,
+    ) -> Result<()> {
+        if let Node::MatchRoot(_) = self {
+            self.select_mut(tag, out);
+            return Ok(());
+        }
+        match (self, tag.tag) {
+            (Node::Key(key), NbtTag::Compound(c)) => {
+                let child = c.entry(key.clone()).or_insert_with(|| default.clone());
+                out.push(TagMut::new(child, None));
+            }
+            (Node::MatchKey(key, pattern), NbtTag::Compound(c)) => {
+                let child = c.entry(key.clone()).or_insert_with(|| pattern.clone());
+                if matches(pattern, child) {
+                    out.push(TagMut::new(child, None));
+                }
+            }
+            (Node::AllElements, NbtTag::List(list)) => {
+                if list.is_empty() {
+                    push(list, default.clone())?;
+                }
+                out.extend(list.iter_mut());
+            }
+            (Node::MatchElements(pattern), NbtTag::List(list)) => {
+                if !list.iter().any(|t| matches(pattern, t)) {
+                    push(list, pattern.clone())?;
+                }
+                out.extend(list.iter_mut().filter(|t| matches(pattern, t)));
+            }
+            (node, tag) => node.select_mut(TagMut::new(tag, None), out),
+        }
+        Ok(())
+    }
+
+    /// Replaces the tags this node selects in `parent` with `value`,
+    /// returning how many actually changed
+    fn set(&self, parent: &mut NbtTag, value: &NbtTag) -> Result<usize> {
+        match (self, parent) {
+            (Node::Key(key), NbtTag::Compound(c)) => {
+                let old = c.insert(key.clone(), value.clone());
+                Ok(usize::from(old.as_ref() != Some(value)))
+            }
+            (Node::MatchKey(key, pattern), NbtTag::Compound(c)) => match c.get_mut(key) {
+                Some(old) if matches(pattern, old) && old != value => {
+                    *old = value.clone();
+                    Ok(1)
+                }
+                _ => Ok(0),
+            },
+            (Node::Index(index), NbtTag::List(list)) => {
+                let Some(i) = resolve_index(*index, list.len()) else {
+                    return Ok(0);
+                };
+                if list[i] == *value {
+                    return Ok(0);
+                }
+                check_elem_type(list, value)?;
+                list.set(i, value.clone())?;
+                Ok(1)
+            }
+            (Node::AllElements, NbtTag::List(list)) if list.is_empty() => {
+                push(list, value.clone())?;
+                Ok(1)
+            }
+            (Node::AllElements, NbtTag::List(list)) => replace_elements(list, value, |_| true),
+            (Node::MatchElements(pattern), NbtTag::List(list)) => {
+                replace_elements(list, value, |t| matches(pattern, t))
+            }
+            // The root has no parent to store a new tag in
+            (Node::MatchRoot(_), _) => Err(Error::Unsupported("Setting the root tag of a path")),
+            _ => Ok(0),
+        }
+    }
+
+    /// Removes the tags this node selects from `parent`, returning how many were removed
+    fn remove(&self, parent: &mut NbtTag) -> usize {
+        match (self, parent) {
+            (Node::Key(key), NbtTag::Compound(c)) => usize::from(remove_key(c, key)),
+            (Node::MatchKey(key, pattern), NbtTag::Compound(c))
+                if c.get(key).is_some_and(|t| matches(pattern, t)) =>
+            {
+                usize::from(remove_key(c, key))
+            }
+            (Node::Index(index), NbtTag::List(list)) => match resolve_index(*index, list.len()) {
+                Some(i) => {
+                    list.remove(i);
+                    1
+                }
+                None => 0,
+            },
+            (Node::AllElements, NbtTag::List(list)) => {
+                let len = list.len();
+                list.clear();
+                len
+            }
+            (Node::MatchElements(pattern), NbtTag::List(list)) => {
+                let len = list.len();
+                list.retain(|t| !matches(pattern, t));
+                len - list.len()
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Replaces the elements of a list accepted by `filter` with `value`,
+/// returning how many actually changed
+fn replace_elements(
+    list: &mut NbtList,
+    value: &NbtTag,
+    filter: impl Fn(&NbtTag) -> bool,
+) -> Result<usize> {
+    let mut count = 0;
+    for i in 0..list.len() {
+        if filter(&list[i]) && list[i] != *value {
+            check_elem_type(list, value)?;
+            list.set(i, value.clone())?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Fails unless `value` can be stored in the list without mixing tag types
+fn check_elem_type(list: &NbtList, value: &NbtTag) -> Result<()> {
+    if list.elem_type() != value.tag_type() {
+        return Err(Error::TypeMismatch {
+            expected: list.elem_type(),
+            actual: value.tag_type(),
+        });
+    }
+    Ok(())
+}
+
+/// Appends a tag to a list, reporting the list's type if the tag does not fit
+fn push(list: &mut NbtList, value: NbtTag) -> Result<()> {
+    let (expected, actual) = (list.elem_type(), value.tag_type());
+    list.push(value)
+        .map_err(|_| Error::TypeMismatch { expected, actual })
+}
+
+/// Removes a tag from a compound, keeping the order of the others
+fn remove_key(compound: &mut NbtCompound, key: &str) -> bool {
+    #[cfg(feature = "preserve_order")]
+    let removed = compound.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    let removed = compound.remove(key);
+    removed.is_some()
+}
+
+/// Merges `source` into `target` like the game does: compounds present in both
+/// are merged recursively, and any other tag of `source` replaces the one in `target`.
+/// Returns whether `target` changed
+fn merge_compound(target: &mut NbtCompound, source: &NbtCompound) -> bool {
+    let mut changed = false;
+    for (key, value) in source {
+        match (target.get_mut(key), value) {
+            (Some(NbtTag::Compound(target)), NbtTag::Compound(source)) => {
+                changed |= merge_compound(target, source);
+            }
+            (Some(old), value) if old == value => {}
+            _ => {
+                target.insert(key.clone(), value.clone());
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Turns a possibly negative index into a position in a list of `len` elements
+pub(crate) fn resolve_index(index: i32, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)?
+    } else {
+        index as usize
+    };
+    (index < len).then_some(index)
+}
+
+/// Whether `tag` matches `pattern` the way the game compares them:
+/// compounds match if they contain every tag of the pattern, and lists
+/// match if every element of the pattern matches one of theirs
+pub(crate) fn matches(pattern: &NbtTag, tag: &NbtTag) -> bool {
+    match (pattern, tag) {
+        (NbtTag::Compound(pattern), NbtTag::Compound(tag)) => pattern
+            .iter()
+            .all(|(k, p)| tag.get(k).is_some_and(|t| matches(p, t))),
+        (NbtTag::List(pattern), NbtTag::List(tag)) if pattern.is_empty() => tag.is_empty(),
+        (NbtTag::List(pattern), NbtTag::List(tag)) => {
+            pattern.iter().all(|p| tag.iter().any(|t| matches(p, t)))
+        }
+        (pattern, tag) => pattern == tag,
+    }
+}
+
+impl NbtTag {
+    /// Returns every tag the path selects, in order
+    pub fn query(&self, path: &NbtPath) -> Vec<&NbtTag> {
+        let mut current = vec![self];
+        for node in &path.nodes {
+            let mut next = Vec::new();
+            for tag in current {
+                node.select(tag, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Returns every tag the path selects for modification, in order.
+    /// Elements of lists can only be replaced by tags of the list's element type
+    pub fn query_mut(&mut self, path: &NbtPath) -> Vec<TagMut<'_>> {
+        let mut current = vec![TagMut::new(self, None)];
+        for node in &path.nodes {
+            let mut next = Vec::new();
+            for tag in current {
+                node.select_mut(tag, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Sets the tags the path selects to `value` like `/data modify ... set`,
+    /// creating missing compounds along the way.
+    /// Returns how many tags changed, and fails for a path of just `{...}`,
+    /// since the root tag cannot be replaced through a path
+    ///
+    /// ```
+    /// use nbt_rust::NbtTag;
+    ///
+    /// let mut item = NbtTag::from_snbt(r#"{id:"minecraft:stone"}"#).unwrap();
+    /// let path = "tag.display.Name".parse().unwrap();
+    /// assert_eq!(item.set(&path, "Rock".to_string()).unwrap(), 1);
+    /// assert_eq!(item["tag"]["display"]["Name"].as_str(), Some("Rock"));
+    /// ```
+    pub fn set(&mut self, path: &NbtPath, value: impl Into<NbtTag>) -> Result<usize> {
+        let value = value.into();
+        let (last, parents) = path.split_last();
+
+        let mut count = 0;
+        for parent in create_path(self, parents, last.preferred_parent())
+            .map_err(|e| e.in_path(&path.source))?
+        {
+            count += last
+                .set(parent.tag, &value)
+                .map_err(|e| e.in_path(&path.source))?;
+        }
+        Ok(count)
+    }
+
+    /// Removes the tags the path selects like `/data remove`.
+    /// Returns how many tags were removed, which is 0 for a path of just `{...}`
+    pub fn remove(&mut self, path: &NbtPath) -> usize {
+        let (last, parents) = path.split_last();
+
+        let mut current = vec![TagMut::new(self, None)];
+        for node in parents {
+            let mut next = Vec::new();
+            for tag in current {
+                node.select_mut(tag, &mut next);
+            }
+            current = next;
+        }
+
+        current
+            .into_iter()
+            .map(|parent| last.remove(parent.tag))
+            .sum()
+    }
+
+    /// Inserts `value` into each list the path selects like `/data modify ... insert`,
+    /// creating missing lists and compounds along the way. A negative index counts
+    /// from the end, so -1 appends. Returns how many lists changed
+    pub fn insert(
+        &mut self,
+        path: &NbtPath,
+        index: i32,
+        value: impl Into<NbtTag>,
+    ) -> Result<usize> {
+        let value = value.into();
+        let targets = create_path(self, &path.nodes, NbtTag::List(NbtList::new_empty()))
+            .map_err(|e| e.in_path(&path.source))?;
+
+        let mut count = 0;
+        for target in targets {
+            let NbtTag::List(list) = target.tag else {
+                let error = Error::TypeMismatch {
+                    expected: TagType::List,
+                    actual: target.tag_type(),
+                };
+                return Err(error.in_path(&path.source));
+            };
+
+            let len = list.len();
+            let position = if index < 0 {
+                len as i64 + index as i64 + 1
+            } else {
+                index as i64
+            };
+            if position < 0 || position > len as i64 {
+                let error = Error::IndexOutOfBounds { index, len };
+                return Err(error.in_path(&path.source));
+            }
+
+            check_insertable(list, &value).map_err(|e| e.in_path(&path.source))?;
+            list.insert(position as usize, value.clone())?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Appends `value` to each list the path selects like `/data modify ... append`
+    pub fn append(&mut self, path: &NbtPath, value: impl Into<NbtTag>) -> Result<usize> {
+        self.insert(path, -1, value)
+    }
+
+    /// Prepends `value` to each list the path selects like `/data modify ... prepend`
+    pub fn prepend(&mut self, path: &NbtPath, value: impl Into<NbtTag>) -> Result<usize> {
+        self.insert(path, 0, value)
+    }
+
+    /// Deeply merges the compound `value` into each compound the path selects
+    /// like `/data modify ... merge`, creating missing compounds along the way.
+    /// Returns how many compounds changed
+    ///
+    /// ```
+    /// use nbt_rust::NbtTag;
+    ///
+    /// let mut entity = NbtTag::from_snbt("{Attributes:{Speed:0.1d},Health:20.0f}").unwrap();
+    /// let patch = NbtTag::from_snbt("{Attributes:{Armor:2.0d},Health:10.0f}").unwrap();
+    /// entity.merge(&"{}".parse().unwrap(), patch).unwrap();
+    /// assert_eq!(entity["Attributes"]["Speed"].as_f64(), Some(0.1));
+    /// assert_eq!(entity["Attributes"]["Armor"].as_f64(), Some(2.0));
+    /// assert_eq!(entity["Health"].as_f32(), Some(10.0));
+    /// ```
+    pub fn merge(&mut self, path: &NbtPath, value: impl Into<NbtTag>) -> Result<usize> {
+        let source = match value.into() {
+            NbtTag::Compound(source) => source,
+            value => {
+                let error = Error::TypeMismatch {
+                    expected: TagType::Compound,
+                    actual: value.tag_type(),
+                };
+                return Err(error.in_path(&path.source));
+            }
+        };
+        let targets = create_path(self, &path.nodes, NbtTag::Compound(NbtCompound::new()))
+            .map_err(|e| e.in_path(&path.source))?;
+
+        let mut count = 0;
+        for target in targets {
+            let NbtTag::Compound(target) = target.tag else {
+                let error = Error::TypeMismatch {
+                    expected: TagType::Compound,
+                    actual: target.tag_type(),
+                };
+                return Err(error.in_path(&path.source));
+            };
+            count += usize::from(merge_compound(target, &source));
+        }
+        Ok(count)
+    }
+}
+
+impl NbtPath {
+    /// The last node of the path and the nodes leading to its parents
+    fn split_last(&self) -> (&Node, &[Node]) {
+        self.nodes
+            .split_last()
+            .expect("a parsed path has at least one node")
+    }
+}
+
+/// Follows `nodes` from `tag`, creating missing tags as the next node prefers them
+/// and the selected tags themselves from `leaf`
+fn create_path<'a>(tag: &'a mut NbtTag, nodes: &[Node], leaf: NbtTag) -> Result<Vec<TagMut<'a>>> {
+    let mut current = vec![TagMut::new(tag, None)];
+    for (i, node) in nodes.iter().enumerate() {
+        let default = match nodes.get(i + 1) {
+            Some(next) => next.preferred_parent(),
+            None => leaf.clone(),
+        };
+
+        let mut next = Vec::new();
+        for tag in current {
+            node.select_or_create(tag, &default, &mut next)?;
+        }
+        current = next;
+    }
+    Ok(current)
+}
+
+/// Fails unless `value` can be added to the list without mixing tag types
+fn check_insertable(list: &NbtList, value: &NbtTag) -> Result<()> {
+    if list.is_empty() && list.elem_type() == TagType::End {
+        return Ok(());
+    }
+    check_elem_type(list, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> NbtPath {
+        NbtPath::parse(s).unwrap()
+    }
+
+    fn player() -> NbtTag {
+        NbtTag::from_snbt(
+            r#"{Inventory: [{Slot: 0b, id: "stone", Count: 64b}, {Slot: 1b, id: "dirt"}],
+                "odd key": {a: [[1, 2], [3]]}, Empty: []}"#,
+        )
+        .unwrap()
+    }
+
+    fn ids(tags: Vec<&NbtTag>) -> Vec<&str> {
+        tags.into_iter().filter_map(NbtTag::as_str).collect()
+    }
+
+    #[test]
+    fn parses_every_node() {
+        let parsed = path(r#"{a:1}.b{c:2b}."quoted.key"[0][-1][][{d:3}].'x'"#);
+        assert!(matches!(
+            parsed.nodes[..],
+            [
+                Node::MatchRoot(_),
+                Node::MatchKey(..),
+                Node::Key(_),
+                Node::Index(0),
+                Node::Index(-1),
+                Node::AllElements,
+                Node::MatchElements(_),
+                Node::Key(_),
+            ]
+        ));
+        assert!(matches!(&parsed.nodes[2], Node::Key(k) if k == "quoted.key"));
+        assert_eq!(
+            parsed.to_string(),
+            r#"{a:1}.b{c:2b}."quoted.key"[0][-1][][{d:3}].'x'"#
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_offending_input() {
+        for (s, message, column) in [
+            ("a..b", "Invalid NBT path element", 3),
+            ("a[x]", "Expected an index", 3),
+            ("a[1", "Expected ']', found end of input", 4),
+            ("", "Invalid NBT path element", 1),
+            ("a b", "Expected '.', found ' '", 2),
+        ] {
+            let Err(Error::Snbt(e)) = NbtPath::parse(s) else {
+                panic!("{s:?} was parsed");
+            };
+            assert_eq!((e.message.as_str(), e.column), (message, column), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn queries_select_in_order() {
+        let player = player();
+        assert_eq!(
+            ids(player.query(&path("Inventory[].id"))),
+            ["stone", "dirt"]
+        );
+        assert_eq!(ids(player.query(&path("Inventory[-1].id"))), ["dirt"]);
+        assert_eq!(
+            ids(player.query(&path("Inventory[{Slot:0b}].id"))),
+            ["stone"]
+        );
+        assert_eq!(player.query(&path(r#""odd key".a[][]"#)).len(), 3);
+        assert_eq!(player.query(&path("{Empty:[]}")).len(), 1);
+        assert_eq!(player.query(&path("Inventory{}")).len(), 0);
+    }
+
+    #[test]
+    fn filters_matching_nothing_select_nothing() {
+        let player = player();
+        for s in [
+            "Inventory[{Slot:5b}]",
+            "Inventory[{id:gravel}]",
+            "Inventory[2]",
+            "Inventory[-3]",
+            "Inventory[].Missing",
+            "Empty[]",
+            "{Empty:[1]}",
+            r#""odd key"{a:[[4]]}"#,
+            "Inventory.id",
+        ] {
+            assert!(player.query(&path(s)).is_empty(), "{s}");
+        }
+        assert!(player
+            .clone()
+            .query_mut(&path("Inventory[{Slot:5b}]"))
+            .is_empty());
+    }
+
+    #[test]
+    fn list_patterns_match_subsets() {
+        let player = player();
+        assert_eq!(player.query(&path(r#""odd key"{a:[[3]]}"#)).len(), 1);
+        assert_eq!(player.query(&path(r#""odd key".a[{}]"#)).len(), 0);
+    }
+
+    #[test]
+    fn query_mut_keeps_list_types() {
+        let mut player = player();
+        for mut slot in player.query_mut(&path("Inventory[].Slot")) {
+            let NbtTag::Byte(v) = *slot else {
+                panic!("not a Byte");
+            };
+            slot.set(NbtTag::Byte(v + 9)).ok().unwrap();
+        }
+        assert!(player["Inventory"][1]["Slot"] == NbtTag::Byte(10));
+
+        let mut elems = player.query_mut(&path("Inventory[0]"));
+        let error = elems[0].set(NbtTag::Int(1)).err().unwrap();
+        assert!(matches!(error, Error::ElementTypesDiffer));
+    }
+
+    fn snbt(s: &str) -> NbtTag {
+        NbtTag::from_snbt(s).unwrap()
+    }
+
+    #[test]
+    fn set_creates_missing_compounds() {
+        let mut item = snbt("{id: stone}");
+        assert_eq!(item.set(&path("tag.display.Name"), "Rock").unwrap(), 1);
+        assert_eq!(item["tag"]["display"]["Name"].as_str(), Some("Rock"));
+        // Setting the same value again changes nothing
+        assert_eq!(item.set(&path("tag.display.Name"), "Rock").unwrap(), 0);
+        assert_eq!(item.set(&path("tag{x:1}.y"), 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn set_keeps_list_types() {
+        let mut player = player();
+        assert_eq!(player.set(&path("Inventory[].Count"), 1i8).unwrap(), 2);
+        assert_eq!(
+            player
+                .set(&path("Inventory[{Slot:1b}].id"), "sand")
+                .unwrap(),
+            1
+        );
+        // Like the game, a missing element matching the filter is created
+        let sand = path("Inventory[{Slot:7b}].id");
+        assert_eq!(player.set(&sand, "sand").unwrap(), 1);
+        assert!(player["Inventory"][2] == snbt(r#"{Slot: 7b, id: "sand"}"#));
+        assert_eq!(player.set(&path("Empty[]"), 5).unwrap(), 1);
+        assert!(player["Empty"][0] == NbtTag::Int(5));
+
+        let error = player.set(&path("Empty[0]"), "five").err().unwrap();
+        assert!(matches!(error.inner(), Error::TypeMismatch { .. }));
+        assert_eq!(error.path(), Some("Empty[0]"));
+    }
+
+    #[test]
+    fn set_on_the_root_fails() {
+        let mut player = player();
+        for s in ["{}", "{Empty:[]}"] {
+            let error = player.set(&path(s), snbt("{}")).err().unwrap();
+            assert!(matches!(error.inner(), Error::Unsupported(_)), "{s}");
+            assert_eq!(error.path(), Some(s));
+        }
+        assert!(player == self::player());
+        assert_eq!(player.remove(&path("{}")), 0);
+    }
+
+    #[test]
+    fn insert_counts_negative_indices_from_the_end() {
+        let mut tag = snbt("{list: [1, 2, 3]}");
+        let list = path("list");
+        tag.insert(&list, -1, 4).unwrap();
+        tag.insert(&list, -2, 9).unwrap();
+        tag.insert(&list, 0, 0).unwrap();
+        tag.prepend(&list, -1).unwrap();
+        tag.append(&list, 5).unwrap();
+        assert!(tag["list"] == snbt("[-1, 0, 1, 2, 3, 9, 4, 5]"));
+
+        for index in [-10, 9] {
+            let error = tag.insert(&list, index, 6).err().unwrap();
+            assert!(matches!(
+                error.inner(),
+                Error::IndexOutOfBounds { len: 8, .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn insert_creates_missing_lists() {
+        let mut tag = snbt("{}");
+        assert_eq!(tag.append(&path("a.b"), "x").unwrap(), 1);
+        assert_eq!(tag["a"]["b"][0].as_str(), Some("x"));
+
+        let error = tag.append(&path("a.b"), 1).err().unwrap();
+        assert!(matches!(error.inner(), Error::TypeMismatch { .. }));
+        let error = tag.append(&path("a"), 1).err().unwrap();
+        assert!(matches!(
+            error.inner(),
+            Error::TypeMismatch {
+                expected: TagType::List,
+                actual: TagType::Compound
+            }
+        ));
+        // A filter matching no list inserts nothing
+        assert_eq!(tag.append(&path("a{c:1}.b"), "y").unwrap(), 0);
+    }
+
+    #[test]
+    fn merge_is_deep() {
+        let mut tag = snbt("{a: {b: 1, c: {d: 2}}, e: [1]}");
+        let patch = snbt("{a: {c: {f: 3}}, e: [2]}");
+        assert_eq!(tag.merge(&path("{}"), patch.clone()).unwrap(), 1);
+        assert!(tag == snbt("{a: {b: 1, c: {d: 2, f: 3}}, e: [2]}"));
+        assert_eq!(tag.merge(&path("{}"), patch).unwrap(), 0);
+
+        assert_eq!(tag.merge(&path("new.nested"), snbt("{x: 1b}")).unwrap(), 1);
+        assert!(tag["new"]["nested"]["x"] == NbtTag::Byte(1));
+
+        let error = tag.merge(&path("a"), 1).err().unwrap();
+        assert!(matches!(error.inner(), Error::TypeMismatch { .. }));
+        let error = tag.merge(&path("e"), snbt("{}")).err().unwrap();
+        assert!(matches!(error.inner(), Error::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn remove_counts_removed_tags() {
+        let mut player = player();
+        assert_eq!(player.remove(&path("Inventory[{Slot:9b}]")), 0);
+        assert_eq!(player.remove(&path("Inventory[].Count")), 1);
+        assert_eq!(player.remove(&path("Inventory[-1]")), 1);
+        assert_eq!(player.remove(&path(r#""odd key".a[]"#)), 2);
+        assert_eq!(player.remove(&path("Missing")), 0);
+        assert!(
+            player
+                == snbt(r#"{Inventory: [{Slot: 0b, id: "stone"}], "odd key": {a: []}, Empty: []}"#)
+        );
+    }
+}
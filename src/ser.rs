@@ -1,22 +1,71 @@
-use std::{collections::HashMap, io};
+use std::{
+    io::{self, Write},
+    marker::PhantomData,
+};
 
 use crate::{
+    compression::{CompressedWriter, Compression},
     error::{Error, Result},
-    nbt_tag::TagType,
+    flavor::{Java, NbtFlavor},
+    mutf8::StringEncoding,
+    nbt_tag::{NbtCompound, NbtList, TagType},
     NbtTag,
 };
 
-pub struct Serializer<W>(W);
+#[cfg(feature = "serde")]
+pub(crate) mod serde_impl;
+#[cfg(feature = "serde")]
+pub use serde_impl::{to_bytes, to_writer};
+
+pub struct Serializer<W: io::Write, F: NbtFlavor = Java> {
+    writer: CompressedWriter<W>,
+    flavor: PhantomData<F>,
+    encoding: StringEncoding,
+    /// Number of lists and compounds being written, the root having no parent
+    depth: usize,
+}
 
 impl<W: io::Write> Serializer<W> {
-    /// Creates a new serializer that writes to the given writer
+    /// Creates a new serializer that writes uncompressed Java NBT to the given writer
     pub fn new(writer: W) -> Self {
-        Self(writer)
+        Self::with_compression(writer, Compression::None)
+    }
+
+    /// Creates a new serializer that compresses its output
+    /// with the given compression before writing it to the writer
+    pub fn with_compression(writer: W, compression: Compression) -> Self {
+        Self {
+            writer: CompressedWriter::new(writer, compression),
+            flavor: PhantomData,
+            encoding: Java::STRING_ENCODING,
+            depth: 0,
+        }
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> Serializer<W, F> {
+    /// Selects the flavor of NBT to write, Java by default.
+    /// This also selects the string encoding of that flavor
+    pub fn with_flavor<G: NbtFlavor>(self, _flavor: G) -> Serializer<W, G> {
+        Serializer {
+            writer: self.writer,
+            flavor: PhantomData,
+            encoding: G::STRING_ENCODING,
+            depth: self.depth,
+        }
+    }
+
+    /// Replaces the encoding strings are written with, Modified UTF-8 by default
+    pub fn with_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.encoding = encoding;
+        self
     }
 
-    /// Consumes the serializer and returns the underlying writer
-    pub fn into_inner(self) -> W {
-        self.0
+    /// Consumes the serializer, finishes the compressed stream (if any)
+    /// and returns the underlying writer. Fails if writing the trailer of the
+    /// compressed stream fails.
+    pub fn into_inner(self) -> Result<W> {
+        Ok(self.writer.finish()?)
     }
 
     /// Serializes a value into NBT
@@ -46,12 +95,11 @@ impl<W: io::Write> Serializer<W> {
     /// Writes the end tag to the underlying writer
     #[inline]
     pub fn serialize_end(&mut self) -> Result<()> {
-        self.0.write_all(&[TagType::End as u8])?;
-        Ok(())
+        self.write_tag_type(TagType::End)
     }
 
     /// Serializes a byte into NBT
-    pub fn serialize_byte(&mut self, k: &str, v: u8) -> Result<()> {
+    pub fn serialize_byte(&mut self, k: &str, v: i8) -> Result<()> {
         self.write_header(TagType::Byte, k)?;
         self.write_byte(v)
     }
@@ -98,14 +146,14 @@ impl<W: io::Write> Serializer<W> {
         self.write_string(v)
     }
 
-    /// Serializes a slice of NBT tags into NBT
-    pub fn serialize_list(&mut self, k: &str, value: &[NbtTag]) -> Result<()> {
+    /// Serializes a list of NBT tags into NBT
+    pub fn serialize_list(&mut self, k: &str, value: &NbtList) -> Result<()> {
         self.write_header(TagType::List, k)?;
         self.write_list(value)
     }
 
     /// Serializes a vector of key-value pairs into NBT
-    pub fn serialize_compound(&mut self, k: &str, v: &HashMap<String, NbtTag>) -> Result<()> {
+    pub fn serialize_compound(&mut self, k: &str, v: &NbtCompound) -> Result<()> {
         self.write_header(TagType::Compound, k)?;
         self.write_compound(v)
     }
@@ -122,66 +170,94 @@ impl<W: io::Write> Serializer<W> {
         self.write_long_array(v)
     }
 
+    /// Serializes a root tag without a name, as Java Edition sends it
+    /// since 1.20.2 (protocol 764): just the type byte followed by the payload
+    pub fn write_unnamed(&mut self, v: &NbtTag) -> Result<()> {
+        self.write_tag_type(v.tag_type())?;
+        match v {
+            NbtTag::End => Ok(()),
+            NbtTag::Byte(v) => self.write_byte(*v),
+            NbtTag::Short(v) => self.write_short(*v),
+            NbtTag::Int(v) => self.write_int(*v),
+            NbtTag::Long(v) => self.write_long(*v),
+            NbtTag::Float(v) => self.write_float(*v),
+            NbtTag::Double(v) => self.write_double(*v),
+            NbtTag::ByteArray(v) => self.write_byte_array(v),
+            NbtTag::String(v) => self.write_string(v),
+            NbtTag::List(v) => self.write_list(v),
+            NbtTag::Compound(v) => self.write_compound(v),
+            NbtTag::IntArray(v) => self.write_int_array(v),
+            NbtTag::LongArray(v) => self.write_long_array(v),
+        }
+    }
+
     /// Consumes the serializer and returns a CompoundSerializer
     /// which can be used to serialize a compound tag
-    pub fn start_compound(mut self, name: &str) -> Result<CompoundSerializer<W>> {
+    pub fn start_compound(mut self, name: &str) -> Result<CompoundSerializer<W, F>> {
         self.write_header(TagType::Compound, name)?;
+        self.depth += 1;
         Ok(CompoundSerializer(self))
     }
 }
 
 /// Headless methods for serializing NBT tags
-impl<W: io::Write> Serializer<W> {
+impl<W: io::Write, F: NbtFlavor> Serializer<W, F> {
     /// Writes a header to the provided tag
     #[inline]
     fn write_header(&mut self, tag_type: TagType, name: &str) -> Result<()> {
-        let mut res = vec![tag_type as u8];
-        res.extend((name.len() as i16).to_be_bytes());
-        res.extend(name.as_bytes());
-        self.0.write_all(&res)?;
+        self.write_tag_type(tag_type)?;
+        if self.depth == 0 && !F::HAS_ROOT_NAME {
+            return Ok(());
+        }
+        self.write_string(name)
+    }
 
+    /// Writes the type prefix of a tag
+    #[inline]
+    fn write_tag_type(&mut self, tag_type: TagType) -> Result<()> {
+        self.writer.write_all(&[tag_type as u8])?;
         Ok(())
     }
 
     /// Headless version of serialize_byte()
     #[inline]
-    fn write_byte(&mut self, v: u8) -> Result<()> {
-        self.0.write_all(&[v])?;
+    fn write_byte(&mut self, v: i8) -> Result<()> {
+        self.writer.write_all(&[v as u8])?;
         Ok(())
     }
 
     /// Headless version of serialize_short()
     #[inline]
     fn write_short(&mut self, v: i16) -> Result<()> {
-        self.0.write_all(&v.to_be_bytes())?;
+        F::write_short(&mut self.writer, v)?;
         Ok(())
     }
 
     /// Headless version of serialize_int()
     #[inline]
     fn write_int(&mut self, v: i32) -> Result<()> {
-        self.0.write_all(&v.to_be_bytes())?;
+        F::write_int(&mut self.writer, v)?;
         Ok(())
     }
 
     /// Headless version of serialize_long()
     #[inline]
     fn write_long(&mut self, v: i64) -> Result<()> {
-        self.0.write_all(&v.to_be_bytes())?;
+        F::write_long(&mut self.writer, v)?;
         Ok(())
     }
 
     /// Headless version of serialize_float()
     #[inline]
     fn write_float(&mut self, v: f32) -> Result<()> {
-        self.0.write_all(&v.to_be_bytes())?;
+        F::write_float(&mut self.writer, v)?;
         Ok(())
     }
 
     /// Headless version of serialize_double()
     #[inline]
     fn write_double(&mut self, v: f64) -> Result<()> {
-        self.0.write_all(&v.to_be_bytes())?;
+        F::write_double(&mut self.writer, v)?;
         Ok(())
     }
 
@@ -189,170 +265,277 @@ impl<W: io::Write> Serializer<W> {
     #[inline]
     fn write_byte_array(&mut self, v: &[u8]) -> Result<()> {
         self.write_int(v.len() as i32)?;
-        self.0.write_all(v)?;
+        self.writer.write_all(v)?;
         Ok(())
     }
 
     /// Headless version of serialize_string()
     #[inline]
     fn write_string(&mut self, v: &str) -> Result<()> {
-        self.write_short(v.len() as i16)?;
-        self.0.write_all(v.as_bytes())?;
+        let bytes = self.encoding.encode(v);
+        F::write_string_len(&mut self.writer, bytes.len())?;
+        self.writer.write_all(&bytes)?;
         Ok(())
     }
 
-    /// Serializes a slice of NBT tags into NBT
+    /// Headless version of serialize_list()
     #[inline]
-    fn write_list(&mut self, value: &[NbtTag]) -> Result<()> {
-        let Some(tag_type) = value.first().map(|t| t.tag_type()) else {
-            return Err(Error::EmptySequence);
-        };
-
-        if !value.iter().all(|x| x.tag_type() == tag_type) {
-            return Err(Error::ElementTypesDiffer);
+    fn write_list(&mut self, value: &NbtList) -> Result<()> {
+        let tag_type = value.elem_type();
+        if let Some(i) = value.iter().position(|x| x.tag_type() != tag_type) {
+            return Err(Error::ElementTypesDiffer.in_index(i));
         }
 
-        let mut res = vec![tag_type as u8];
-        res.extend((value.len() as i32).to_be_bytes());
-        self.0.write_all(&res)?;
+        self.write_tag_type(tag_type)?;
+        self.write_int(value.len() as i32)?;
 
-        match tag_type {
-            TagType::Byte => {
-                for i in value {
-                    if let NbtTag::Byte(v) = i {
-                        self.write_byte(*v)?;
+        self.nested(|ser| {
+            match tag_type {
+                TagType::Byte => {
+                    for i in value {
+                        if let NbtTag::Byte(v) = i {
+                            ser.write_byte(*v)?;
+                        }
                     }
                 }
-            }
-            TagType::Short => {
-                for i in value {
-                    if let NbtTag::Short(v) = i {
-                        self.write_short(*v)?;
+                TagType::Short => {
+                    for i in value {
+                        if let NbtTag::Short(v) = i {
+                            ser.write_short(*v)?;
+                        }
                     }
                 }
-            }
-            TagType::Int => {
-                for i in value {
-                    if let NbtTag::Int(v) = i {
-                        self.write_int(*v)?;
+                TagType::Int => {
+                    for i in value {
+                        if let NbtTag::Int(v) = i {
+                            ser.write_int(*v)?;
+                        }
                     }
                 }
-            }
-            TagType::Long => {
-                for i in value {
-                    if let NbtTag::Long(v) = i {
-                        self.write_long(*v)?;
+                TagType::Long => {
+                    for i in value {
+                        if let NbtTag::Long(v) = i {
+                            ser.write_long(*v)?;
+                        }
                     }
                 }
-            }
-            TagType::Float => {
-                for i in value {
-                    if let NbtTag::Float(v) = i {
-                        self.write_float(*v)?;
+                TagType::Float => {
+                    for i in value {
+                        if let NbtTag::Float(v) = i {
+                            ser.write_float(*v)?;
+                        }
                     }
                 }
-            }
-            TagType::Double => {
-                for i in value {
-                    if let NbtTag::Double(v) = i {
-                        self.write_double(*v)?;
+                TagType::Double => {
+                    for i in value {
+                        if let NbtTag::Double(v) = i {
+                            ser.write_double(*v)?;
+                        }
                     }
                 }
-            }
-            TagType::ByteArray => {
-                for i in value {
-                    if let NbtTag::ByteArray(v) = i {
-                        self.write_byte_array(v)?;
+                TagType::ByteArray => {
+                    for i in value {
+                        if let NbtTag::ByteArray(v) = i {
+                            ser.write_byte_array(v)?;
+                        }
                     }
                 }
-            }
-            TagType::String => {
-                for i in value {
-                    if let NbtTag::String(v) = i {
-                        self.write_string(v)?;
+                TagType::String => {
+                    for i in value {
+                        if let NbtTag::String(v) = i {
+                            ser.write_string(v)?;
+                        }
                     }
                 }
-            }
-            TagType::List => {
-                for i in value {
-                    if let NbtTag::List(v) = i {
-                        self.write_list(v)?;
+                TagType::List => {
+                    for (i, tag) in value.iter().enumerate() {
+                        if let NbtTag::List(v) = tag {
+                            ser.write_list(v).map_err(|e| e.in_index(i))?;
+                        }
                     }
                 }
-            }
-            TagType::Compound => {
-                for i in value {
-                    if let NbtTag::Compound(v) = i {
-                        self.write_compound(v)?;
+                TagType::Compound => {
+                    for (i, tag) in value.iter().enumerate() {
+                        if let NbtTag::Compound(v) = tag {
+                            ser.write_compound(v).map_err(|e| e.in_index(i))?;
+                        }
                     }
                 }
-            }
-            TagType::IntArray => {
-                for i in value {
-                    if let NbtTag::IntArray(v) = i {
-                        self.write_int_array(v)?;
+                TagType::IntArray => {
+                    for i in value {
+                        if let NbtTag::IntArray(v) = i {
+                            ser.write_int_array(v)?;
+                        }
                     }
                 }
-            }
-            TagType::LongArray => {
-                for i in value {
-                    if let NbtTag::LongArray(v) = i {
-                        self.write_long_array(v)?;
+                TagType::LongArray => {
+                    for i in value {
+                        if let NbtTag::LongArray(v) = i {
+                            ser.write_long_array(v)?;
+                        }
                     }
                 }
+                TagType::End if value.is_empty() => {}
+                TagType::End => {
+                    return Err(Error::Unsupported("List of End tags"));
+                }
             }
-            _ => {
-                return Err(Error::UnknownTagType(tag_type as u8));
-            }
-        }
-
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Headless version of serialize_compound()
-    fn write_compound(&mut self, v: &HashMap<String, NbtTag>) -> Result<()> {
-        for (name, tag) in v {
-            self.serialize_tag(&name.to_string(), tag)?;
-        }
-        self.0.write_all(&[TagType::End as u8])?;
+    fn write_compound(&mut self, v: &NbtCompound) -> Result<()> {
+        self.nested(|ser| {
+            v.iter().try_for_each(|(name, tag)| {
+                ser.serialize_tag(name, tag).map_err(|e| e.in_key(name))
+            })
+        })?;
+        self.writer.write_all(&[TagType::End as u8])?;
 
         Ok(())
     }
 
+    /// Runs `f` one list or compound deeper, restoring the depth even if it fails
+    #[inline]
+    fn nested(&mut self, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
     /// Headless version of serialize_int_array()
     fn write_int_array(&mut self, v: &[i32]) -> Result<()> {
-        let mut res = (v.len() as i32).to_be_bytes().to_vec();
-        for i in v {
-            res.extend(i.to_be_bytes());
+        let mut res = Vec::with_capacity(v.len() * 4);
+        for &i in v {
+            F::write_int(&mut res, i)?;
         }
-        self.0.write_all(&res)?;
+        self.write_int(v.len() as i32)?;
+        self.writer.write_all(&res)?;
 
         Ok(())
     }
 
     fn write_long_array(&mut self, v: &[i64]) -> Result<()> {
-        let mut res = (v.len() as i32).to_be_bytes().to_vec();
-        for i in v {
-            res.extend(i.to_be_bytes());
+        let mut res = Vec::with_capacity(v.len() * 8);
+        for &i in v {
+            F::write_long(&mut res, i)?;
         }
-        self.0.write_all(&res)?;
+        self.write_int(v.len() as i32)?;
+        self.writer.write_all(&res)?;
 
         Ok(())
     }
 }
 
 /// A serializer for compound tags
-pub struct CompoundSerializer<W>(Serializer<W>);
+pub struct CompoundSerializer<W: io::Write, F: NbtFlavor = Java>(Serializer<W, F>);
 
-impl<W: io::Write> CompoundSerializer<W> {
+impl<W: io::Write, F: NbtFlavor> CompoundSerializer<W, F> {
     /// Serializes a key-value pair into NBT
     pub fn write_field<T: Into<NbtTag>>(&mut self, key: &str, value: T) -> Result<()> {
         self.0.serialize_tag(key, &value.into())
     }
 
     /// Consumes the compound serializer and returns the underlying Serializer
-    pub fn end(mut self) -> Result<Serializer<W>> {
+    pub fn end(mut self) -> Result<Serializer<W, F>> {
+        self.0.depth -= 1;
         self.0.serialize_end()?;
         Ok(self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flavor::JavaNetwork;
+
+    fn root() -> NbtTag {
+        NbtTag::from_snbt("{a:{b:[1,2]}}").unwrap()
+    }
+
+    fn written(ser: &Serializer<Vec<u8>, JavaNetwork>) -> usize {
+        match &ser.writer {
+            CompressedWriter::None(bytes) => bytes.len(),
+            _ => unreachable!("the tests write uncompressed"),
+        }
+    }
+
+    fn network_bytes(tag: &NbtTag) -> Vec<u8> {
+        let mut ser = Serializer::new(Vec::new()).with_flavor(JavaNetwork);
+        ser.serialize_tag("", tag).unwrap();
+        ser.into_inner().unwrap()
+    }
+
+    #[test]
+    fn failed_write_restores_depth() {
+        let mixed = NbtList::from_tags_unchecked(vec![NbtTag::Int(1), NbtTag::Byte(2)]);
+        let mut bad = root();
+        let a = bad["a"].as_compound_mut().unwrap();
+        a.insert("c".to_string(), NbtTag::List(mixed));
+
+        let mut ser = Serializer::new(Vec::new()).with_flavor(JavaNetwork);
+        let error = ser.serialize_tag("", &bad).unwrap_err();
+        assert!(matches!(error.inner(), Error::ElementTypesDiffer));
+        assert_eq!(error.path(), Some("a.c[1]"));
+        assert_eq!(ser.depth, 0);
+
+        // The next root is written without a name, as a nameless root should be
+        let written = written(&ser);
+        ser.serialize_tag("", &root()).unwrap();
+        let bytes = ser.into_inner().unwrap();
+        assert_eq!(bytes[written..], network_bytes(&root()));
+    }
+
+    fn unnamed(tag: &NbtTag) -> Vec<u8> {
+        let mut ser = Serializer::new(Vec::new());
+        ser.write_unnamed(tag).unwrap();
+        ser.into_inner().unwrap()
+    }
+
+    #[test]
+    fn unnamed_roots_have_no_name_length() {
+        let compound = NbtTag::from_snbt("{a:1b}").unwrap();
+        let bytes = [
+            TagType::Compound as u8,
+            TagType::Byte as u8,
+            0,
+            1,
+            b'a',
+            1,
+            0,
+        ];
+        assert_eq!(unnamed(&compound), bytes);
+
+        assert_eq!(unnamed(&NbtTag::Int(5)), [TagType::Int as u8, 0, 0, 0, 5]);
+        let string = NbtTag::String("h\u{e9}".to_string());
+        let bytes = [TagType::String as u8, 0, 3, b'h', 0xC3, 0xA9];
+        assert_eq!(unnamed(&string), bytes);
+
+        // The same as a nameless root in the network flavor
+        assert_eq!(unnamed(&root()), network_bytes(&root()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn failed_serde_write_restores_depth() {
+        #[derive(serde::Serialize)]
+        struct Bad {
+            nested: Vec<Vec<Option<i32>>>,
+        }
+
+        let mut ser = Serializer::new(Vec::new()).with_flavor(JavaNetwork);
+        let bad = Bad {
+            nested: vec![vec![Some(1), None]],
+        };
+        let error = ser.serialize_value("", &bad).unwrap_err();
+        assert!(matches!(error.inner(), Error::Unsupported(_)));
+        assert_eq!(ser.depth, 0);
+
+        let written = written(&ser);
+        ser.serialize_value("", &root()).unwrap();
+        let bytes = ser.into_inner().unwrap();
+        assert_eq!(bytes[written..], network_bytes(&root()));
+    }
+}
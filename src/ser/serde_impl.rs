@@ -0,0 +1,1146 @@
+use std::io;
+
+use serde::{ser, Serialize};
+
+use super::Serializer;
+use crate::{
+    error::{Error, Result},
+    flavor::NbtFlavor,
+    nbt_tag::TagType,
+    serde_with::{empty_list_type, forced_type, ElementSerializer},
+};
+
+/// Serializes a value as a named root tag and writes it to the writer
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    name: &str,
+    value: &T,
+) -> Result<()> {
+    let mut ser = Serializer::new(writer);
+    ser.serialize_value(name, value)?;
+    ser.into_inner()?;
+    Ok(())
+}
+
+/// Serializes a value as a named root tag into a byte vector
+pub fn to_bytes<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    to_writer(&mut bytes, name, value)?;
+    Ok(bytes)
+}
+
+impl<W: io::Write, F: NbtFlavor> Serializer<W, F> {
+    /// Serializes any `serde::Serialize` value into NBT
+    ///
+    /// Structs and maps become Compound tags, sequences and tuples become
+    /// List tags, byte buffers become ByteArray tags and `bool` becomes a Byte.
+    /// Fields set to `None` are omitted from their compound. Fields using the
+    /// [`byte_array`](crate::byte_array), [`int_array`](crate::int_array),
+    /// [`long_array`](crate::long_array) or [`list`](crate::list) helpers are
+    /// written as that tag type instead.
+    pub fn serialize_value<T: Serialize + ?Sized>(&mut self, k: &str, v: &T) -> Result<()> {
+        // Serde gives up on the first error without ending the lists and
+        // compounds it started, so the depth is restored here
+        let depth = self.depth;
+        let result = v.serialize(ValueSerializer {
+            ser: &mut *self,
+            slot: Slot::Named(k),
+        });
+        self.depth = depth;
+        result
+    }
+}
+
+/// Where the value being serialized ends up
+enum Slot<'a> {
+    /// A named tag at the root or inside a compound
+    Named(&'a str),
+    /// An element of a list; the element type is written before the first element
+    Element(&'a mut ListState),
+}
+
+struct ListState {
+    len: usize,
+    count: usize,
+    elem_type: Option<TagType>,
+}
+
+impl ListState {
+    fn begin<W: io::Write, F: NbtFlavor>(
+        &mut self,
+        ser: &mut Serializer<W, F>,
+        tag_type: TagType,
+    ) -> Result<()> {
+        match self.elem_type {
+            None => {
+                ser.write_tag_type(tag_type)?;
+                ser.write_int(self.len as i32)?;
+                self.elem_type = Some(tag_type);
+            }
+            Some(elem_type) if elem_type != tag_type => return Err(Error::ElementTypesDiffer),
+            Some(_) => {}
+        }
+
+        self.count += 1;
+        Ok(())
+    }
+
+    fn end<W: io::Write, F: NbtFlavor>(&self, ser: &mut Serializer<W, F>) -> Result<()> {
+        if self.elem_type.is_none() {
+            ser.write_tag_type(TagType::End)?;
+            ser.write_int(0)?;
+        }
+
+        if self.count != self.len {
+            return Err(Error::Message(format!(
+                "expected {} list elements, got {}",
+                self.len, self.count
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+struct ValueSerializer<'a, W: io::Write, F: NbtFlavor> {
+    ser: &'a mut Serializer<W, F>,
+    slot: Slot<'a>,
+}
+
+impl<W: io::Write, F: NbtFlavor> ValueSerializer<'_, W, F> {
+    /// Writes whatever has to precede the payload of a tag of the given type
+    fn begin(&mut self, tag_type: TagType) -> Result<()> {
+        match &mut self.slot {
+            Slot::Named(name) => self.ser.write_header(tag_type, name),
+            Slot::Element(state) => state.begin(self.ser, tag_type),
+        }
+    }
+
+    /// Writes nothing, which is only possible for named tags
+    fn omit(self) -> Result<()> {
+        match self.slot {
+            Slot::Named(_) => Ok(()),
+            Slot::Element(_) => Err(Error::Unsupported("None or unit inside a list")),
+        }
+    }
+}
+
+impl<'a, W: io::Write, F: NbtFlavor> ser::Serializer for ValueSerializer<'a, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SeqSerializer<'a, W, F>;
+    type SerializeTuple = SeqSerializer<'a, W, F>;
+    type SerializeTupleStruct = SeqSerializer<'a, W, F>;
+    type SerializeTupleVariant = SeqSerializer<'a, W, F>;
+    type SerializeMap = MapSerializer<'a, W, F>;
+    type SerializeStruct = StructSerializer<'a, W, F>;
+    type SerializeStructVariant = StructSerializer<'a, W, F>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.serialize_i8(v as i8)
+    }
+
+    fn serialize_i8(mut self, v: i8) -> Result<()> {
+        self.begin(TagType::Byte)?;
+        self.ser.write_byte(v)
+    }
+
+    fn serialize_i16(mut self, v: i16) -> Result<()> {
+        self.begin(TagType::Short)?;
+        self.ser.write_short(v)
+    }
+
+    fn serialize_i32(mut self, v: i32) -> Result<()> {
+        self.begin(TagType::Int)?;
+        self.ser.write_int(v)
+    }
+
+    fn serialize_i64(mut self, v: i64) -> Result<()> {
+        self.begin(TagType::Long)?;
+        self.ser.write_long(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_i8(v as i8)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_i16(v as i16)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(mut self, v: f32) -> Result<()> {
+        self.begin(TagType::Float)?;
+        self.ser.write_float(v)
+    }
+
+    fn serialize_f64(mut self, v: f64) -> Result<()> {
+        self.begin(TagType::Double)?;
+        self.ser.write_double(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(mut self, v: &str) -> Result<()> {
+        self.begin(TagType::String)?;
+        self.ser.write_string(v)
+    }
+
+    fn serialize_bytes(mut self, v: &[u8]) -> Result<()> {
+        self.begin(TagType::ByteArray)?;
+        self.ser.write_byte_array(v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.omit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.omit()
+    }
+
+    fn serialize_unit_struct(mut self, _name: &'static str) -> Result<()> {
+        self.begin(TagType::Compound)?;
+        self.ser.serialize_end()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if let Some(elem_type) = empty_list_type(name) {
+            self.begin(TagType::List)?;
+            self.ser.write_tag_type(elem_type)?;
+            return self.ser.write_int(0);
+        }
+        match forced_type(name) {
+            Some(tag_type) => value.serialize(ForcedSerializer {
+                inner: self,
+                tag_type,
+            }),
+            None => value.serialize(self),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.begin(TagType::Compound)?;
+        self.ser.depth += 1;
+        value
+            .serialize(ValueSerializer {
+                ser: &mut *self.ser,
+                slot: Slot::Named(variant),
+            })
+            .map_err(|e| e.in_key(variant))?;
+        self.ser.depth -= 1;
+        self.ser.serialize_end()
+    }
+
+    fn serialize_seq(mut self, len: Option<usize>) -> Result<SeqSerializer<'a, W, F>> {
+        let len = len.ok_or(Error::UnknownLength)?;
+        self.begin(TagType::List)?;
+        Ok(SeqSerializer::new(self.ser, len, None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a, W, F>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a, W, F>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a, W, F>> {
+        self.begin(TagType::Compound)?;
+        self.ser.depth += 1;
+        self.ser.write_header(TagType::List, variant)?;
+        Ok(SeqSerializer::new(self.ser, len, Some(variant)))
+    }
+
+    fn serialize_map(mut self, _len: Option<usize>) -> Result<MapSerializer<'a, W, F>> {
+        self.begin(TagType::Compound)?;
+        self.ser.depth += 1;
+        Ok(MapSerializer {
+            ser: self.ser,
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        mut self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer<'a, W, F>> {
+        self.begin(TagType::Compound)?;
+        self.ser.depth += 1;
+        Ok(StructSerializer {
+            ser: self.ser,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer<'a, W, F>> {
+        self.begin(TagType::Compound)?;
+        self.ser.depth += 1;
+        self.ser.write_header(TagType::Compound, variant)?;
+        self.ser.depth += 1;
+        Ok(StructSerializer {
+            ser: self.ser,
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Serializes a sequence as the tag type selected by one of the `serde_with` helpers
+struct ForcedSerializer<'a, W: io::Write, F: NbtFlavor> {
+    inner: ValueSerializer<'a, W, F>,
+    tag_type: TagType,
+}
+
+impl<'a, W: io::Write, F: NbtFlavor> ForcedSerializer<'a, W, F> {
+    fn mismatch(&self, actual: TagType) -> Error {
+        Error::TypeMismatch {
+            expected: self.tag_type,
+            actual,
+        }
+    }
+
+    fn array(mut self, len: Option<usize>) -> Result<ForcedSeq<'a, W, F>> {
+        if self.tag_type == TagType::List {
+            return ser::Serializer::serialize_seq(self.inner, len).map(ForcedSeq::List);
+        }
+        let len = len.ok_or(Error::UnknownLength)?;
+        self.inner.begin(self.tag_type)?;
+        self.inner.ser.write_int(len as i32)?;
+        Ok(ForcedSeq::Array(ArraySerializer {
+            ser: self.inner.ser,
+            tag_type: self.tag_type,
+            len,
+            count: 0,
+        }))
+    }
+}
+
+impl<'a, W: io::Write, F: NbtFlavor> ser::Serializer for ForcedSerializer<'a, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = ForcedSeq<'a, W, F>;
+    type SerializeTuple = ForcedSeq<'a, W, F>;
+    type SerializeTupleStruct = ForcedSeq<'a, W, F>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = ser::Impossible<(), Error>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<()> {
+        Err(self.mismatch(TagType::Byte))
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<()> {
+        Err(self.mismatch(TagType::Byte))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<()> {
+        Err(self.mismatch(TagType::Short))
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<()> {
+        Err(self.mismatch(TagType::Int))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<()> {
+        Err(self.mismatch(TagType::Long))
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<()> {
+        Err(self.mismatch(TagType::Byte))
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<()> {
+        Err(self.mismatch(TagType::Short))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<()> {
+        Err(self.mismatch(TagType::Int))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<()> {
+        Err(self.mismatch(TagType::Long))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        Err(self.mismatch(TagType::Float))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        Err(self.mismatch(TagType::Double))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(self.mismatch(TagType::String))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<()> {
+        Err(self.mismatch(TagType::String))
+    }
+
+    fn serialize_bytes(mut self, v: &[u8]) -> Result<()> {
+        match self.tag_type {
+            TagType::ByteArray => ser::Serializer::serialize_bytes(self.inner, v),
+            TagType::List => {
+                self.inner.begin(TagType::List)?;
+                let ser = self.inner.ser;
+                ser.write_tag_type(TagType::Byte)?;
+                ser.write_int(v.len() as i32)?;
+                v.iter().try_for_each(|&b| ser.write_byte(b as i8))
+            }
+            _ => Err(self.mismatch(TagType::ByteArray)),
+        }
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.inner.omit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.inner.omit()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        Err(self.mismatch(TagType::String))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ForcedSeq<'a, W, F>> {
+        self.array(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ForcedSeq<'a, W, F>> {
+        self.array(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ForcedSeq<'a, W, F>> {
+        self.array(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(self.mismatch(TagType::Compound))
+    }
+}
+
+/// Serializes the elements of a sequence with a forced tag type
+enum ForcedSeq<'a, W: io::Write, F: NbtFlavor> {
+    List(SeqSerializer<'a, W, F>),
+    Array(ArraySerializer<'a, W, F>),
+}
+
+impl<W: io::Write, F: NbtFlavor> ForcedSeq<'_, W, F> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match self {
+            ForcedSeq::List(seq) => seq.element(value),
+            ForcedSeq::Array(array) => array.element(value),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            ForcedSeq::List(seq) => seq.finish(),
+            ForcedSeq::Array(array) => array.finish(),
+        }
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeSeq for ForcedSeq<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeTuple for ForcedSeq<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeTupleStruct for ForcedSeq<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Writes the elements of a ByteArray, IntArray or LongArray tag as they are serialized
+struct ArraySerializer<'a, W: io::Write, F: NbtFlavor> {
+    ser: &'a mut Serializer<W, F>,
+    tag_type: TagType,
+    len: usize,
+    count: usize,
+}
+
+impl<W: io::Write, F: NbtFlavor> ArraySerializer<'_, W, F> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let index = self.count;
+        if index == self.len {
+            return Err(Error::Message(format!(
+                "expected {} array elements, got more",
+                self.len
+            )));
+        }
+        let v = value
+            .serialize(ElementSerializer {
+                array: self.tag_type,
+            })
+            .map_err(|e| e.in_index(index))?;
+        match self.tag_type {
+            TagType::ByteArray => self.ser.write_byte(v as i8)?,
+            TagType::IntArray => self.ser.write_int(v as i32)?,
+            _ => self.ser.write_long(v)?,
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.count != self.len {
+            return Err(Error::Message(format!(
+                "expected {} array elements, got {}",
+                self.len, self.count
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Records the enum variant wrapping a list or struct in the path of an error
+fn in_variant(error: Error, variant: Option<&str>) -> Error {
+    match variant {
+        Some(variant) => error.in_key(variant),
+        None => error,
+    }
+}
+
+/// Serializes sequences and tuples into a List tag
+struct SeqSerializer<'a, W: io::Write, F: NbtFlavor> {
+    ser: &'a mut Serializer<W, F>,
+    state: ListState,
+    /// Name of the enum variant whose compound wraps the list, if any
+    variant: Option<&'static str>,
+}
+
+impl<'a, W: io::Write, F: NbtFlavor> SeqSerializer<'a, W, F> {
+    fn new(ser: &'a mut Serializer<W, F>, len: usize, variant: Option<&'static str>) -> Self {
+        ser.depth += 1;
+        Self {
+            ser,
+            state: ListState {
+                len,
+                count: 0,
+                elem_type: None,
+            },
+            variant,
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        // Every previous element has been written, so the count is this element's index
+        let index = self.state.count;
+        value
+            .serialize(ValueSerializer {
+                ser: &mut *self.ser,
+                slot: Slot::Element(&mut self.state),
+            })
+            .map_err(|e| in_variant(e.in_index(index), self.variant))
+    }
+
+    fn finish(self) -> Result<()> {
+        self.state.end(self.ser)?;
+        self.ser.depth -= 1;
+        if self.variant.is_some() {
+            self.ser.depth -= 1;
+            self.ser.serialize_end()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeSeq for SeqSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeTuple for SeqSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeTupleStruct for SeqSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeTupleVariant for SeqSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Serializes structs into a Compound tag
+struct StructSerializer<'a, W: io::Write, F: NbtFlavor> {
+    ser: &'a mut Serializer<W, F>,
+    /// Name of the enum variant whose compound wraps the struct, if any
+    variant: Option<&'static str>,
+}
+
+impl<W: io::Write, F: NbtFlavor> StructSerializer<'_, W, F> {
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        value
+            .serialize(ValueSerializer {
+                ser: &mut *self.ser,
+                slot: Slot::Named(key),
+            })
+            .map_err(|e| in_variant(e.in_key(key), self.variant))
+    }
+
+    fn finish(self) -> Result<()> {
+        self.ser.depth -= 1;
+        self.ser.serialize_end()?;
+        if self.variant.is_some() {
+            self.ser.depth -= 1;
+            self.ser.serialize_end()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeStruct for StructSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeStructVariant for StructSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Serializes maps with string keys into a Compound tag
+struct MapSerializer<'a, W: io::Write, F: NbtFlavor> {
+    ser: &'a mut Serializer<W, F>,
+    key: Option<String>,
+}
+
+impl<W: io::Write, F: NbtFlavor> ser::SerializeMap for MapSerializer<'_, W, F> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().ok_or_else(|| {
+            Error::Message("serialize_value called before serialize_key".to_string())
+        })?;
+
+        value
+            .serialize(ValueSerializer {
+                ser: &mut *self.ser,
+                slot: Slot::Named(&key),
+            })
+            .map_err(|e| e.in_key(&key))
+    }
+
+    fn end(self) -> Result<()> {
+        self.ser.depth -= 1;
+        self.ser.serialize_end()
+    }
+}
+
+/// Turns map keys into tag names, accepting only string-like keys
+pub(crate) struct MapKeySerializer;
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::KeyMustBeString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::KeyMustBeString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{de::from_bytes as read_tag, from_bytes, to_value, NbtTag};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Action {
+        Idle,
+        Walk(f32),
+        Jump { height: f64 },
+        Look(i16, i16),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Mob {
+        id: String,
+        health: f32,
+        age: i64,
+        on_ground: bool,
+        flags: u8,
+        pos: (f64, f64, f64),
+        tags: Vec<String>,
+        #[serde(with = "serde_bytes_like")]
+        data: Vec<u8>,
+        custom_name: Option<String>,
+        actions: Vec<Action>,
+        attributes: HashMap<String, i32>,
+    }
+
+    /// Writes the bytes with `serialize_bytes`, as `serde_bytes` does
+    mod serde_bytes_like {
+        pub fn serialize<S: serde::Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(v)
+        }
+
+        pub fn deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+            serde::Deserialize::deserialize(d)
+        }
+    }
+
+    fn mob() -> Mob {
+        Mob {
+            id: "minecraft:zombie".into(),
+            health: 20.0,
+            age: -1,
+            on_ground: true,
+            flags: 200,
+            pos: (0.5, 64.0, -3.25),
+            tags: vec!["boss".into()],
+            data: vec![0, 255],
+            custom_name: None,
+            actions: vec![
+                Action::Walk(0.5),
+                Action::Jump { height: 1.25 },
+                Action::Look(90, -45),
+            ],
+            attributes: HashMap::from([("armor".into(), 4)]),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let bytes = to_bytes("mob", &mob()).unwrap();
+        let (name, tag) = read_tag(&bytes).unwrap();
+        assert_eq!(name, "mob");
+        assert!(tag == to_value(&mob()).unwrap());
+        assert_eq!(from_bytes::<Mob>(&bytes).unwrap(), mob());
+    }
+
+    #[test]
+    fn lays_out_values_as_tags() {
+        let (_, tag) = read_tag(&to_bytes("", &mob()).unwrap()).unwrap();
+        assert!(tag["on_ground"] == NbtTag::Byte(1));
+        assert!(tag["flags"] == NbtTag::Byte(200u8 as i8));
+        assert!(tag["data"] == NbtTag::ByteArray(vec![0, 255]));
+        assert_eq!(tag["pos"].as_list().unwrap().elem_type(), TagType::Double);
+        assert!(tag.get("custom_name").is_none());
+
+        // Unit variants are their name, others a compound named after the variant
+        let actions = &tag["actions"];
+        assert_eq!(actions.as_list().unwrap().elem_type(), TagType::Compound);
+        assert_eq!(actions[0]["Walk"].as_f32(), Some(0.5));
+        assert_eq!(actions[1]["Jump"]["height"].as_f64(), Some(1.25));
+        assert!(actions[2]["Look"][1] == NbtTag::Short(-45));
+    }
+
+    #[test]
+    fn unit_variants_are_strings() {
+        let (_, tag) = read_tag(&to_bytes("", &vec![Action::Idle]).unwrap()).unwrap();
+        assert_eq!(tag[0].as_str(), Some("Idle"));
+
+        // A list cannot hold both the String of a unit variant and a Compound
+        let error = to_bytes("", &vec![Action::Idle, Action::Walk(1.0)]).unwrap_err();
+        assert!(matches!(error.inner(), Error::ElementTypesDiffer));
+    }
+
+    #[test]
+    fn sequences_need_a_length() {
+        struct Evens;
+
+        impl Serialize for Evens {
+            fn serialize<S: ser::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+                s.collect_seq((0..10).filter(|i| i % 2 == 0))
+            }
+        }
+
+        let error = to_bytes("", &Evens).unwrap_err();
+        assert!(matches!(error.inner(), Error::UnknownLength));
+    }
+
+    #[test]
+    fn map_keys_must_be_strings() {
+        let error = to_bytes("", &HashMap::from([(1, 2)])).unwrap_err();
+        assert!(matches!(error.inner(), Error::KeyMustBeString));
+    }
+
+    #[test]
+    fn none_inside_a_list_is_rejected() {
+        #[derive(Serialize)]
+        struct Slots {
+            items: Vec<Option<i8>>,
+        }
+
+        let slots = Slots {
+            items: vec![Some(1), None],
+        };
+        let error = to_bytes("", &slots).unwrap_err();
+        assert!(matches!(error.inner(), Error::Unsupported(_)));
+        assert_eq!(error.path(), Some("items[1]"));
+    }
+
+    #[test]
+    fn mixed_lists_are_rejected() {
+        let error = to_bytes("", &(1, "two")).unwrap_err();
+        assert!(matches!(error.inner(), Error::ElementTypesDiffer));
+        assert_eq!(error.path(), Some("[1]"));
+    }
+}
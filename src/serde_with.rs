@@ -0,0 +1,531 @@
+//! Field helpers for `#[serde(with = "...")]` selecting the tag type of a sequence.
+//!
+//! The helpers wrap the value in a newtype struct with a reserved name, which the
+//! serializers of this crate recognize. Other serde formats see a plain newtype
+//! struct and serialize the value as usual.
+
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, Deserialize, Deserializer, Visitor},
+    ser::{self, Impossible, Serialize},
+};
+
+use crate::{
+    error::{Error, Result},
+    nbt_tag::TagType,
+};
+
+pub(crate) const BYTE_ARRAY: &str = "$nbt_rust::ByteArray";
+pub(crate) const INT_ARRAY: &str = "$nbt_rust::IntArray";
+pub(crate) const LONG_ARRAY: &str = "$nbt_rust::LongArray";
+const LIST: &str = "$nbt_rust::List";
+/// The newtype struct name `NbtTag` deserializes itself with, asking this crate's
+/// deserializers to tell array tags apart from List tags
+pub(crate) const TAG: &str = "$nbt_rust::Tag";
+
+/// Newtype struct names carrying the element type of an empty list, indexed by
+/// tag type, so that serializing an `NbtTag` does not turn it into a List of End tags
+const EMPTY_LISTS: [&str; 13] = [
+    "$nbt_rust::EmptyList<End>",
+    "$nbt_rust::EmptyList<Byte>",
+    "$nbt_rust::EmptyList<Short>",
+    "$nbt_rust::EmptyList<Int>",
+    "$nbt_rust::EmptyList<Long>",
+    "$nbt_rust::EmptyList<Float>",
+    "$nbt_rust::EmptyList<Double>",
+    "$nbt_rust::EmptyList<ByteArray>",
+    "$nbt_rust::EmptyList<String>",
+    "$nbt_rust::EmptyList<List>",
+    "$nbt_rust::EmptyList<Compound>",
+    "$nbt_rust::EmptyList<IntArray>",
+    "$nbt_rust::EmptyList<LongArray>",
+];
+
+/// The reserved name of an empty list of the given element type
+pub(crate) fn empty_list_name(elem_type: TagType) -> &'static str {
+    EMPTY_LISTS[elem_type as usize]
+}
+
+/// The element type of the empty list a reserved newtype struct name stands for
+pub(crate) fn empty_list_type(name: &str) -> Option<TagType> {
+    let id = EMPTY_LISTS.iter().position(|n| *n == name)?;
+    TagType::try_from(id as u8).ok()
+}
+
+/// The tag type a newtype struct name reserved by the helpers selects
+pub(crate) fn forced_type(name: &str) -> Option<TagType> {
+    match name {
+        BYTE_ARRAY => Some(TagType::ByteArray),
+        INT_ARRAY => Some(TagType::IntArray),
+        LONG_ARRAY => Some(TagType::LongArray),
+        LIST => Some(TagType::List),
+        _ => None,
+    }
+}
+
+/// The reserved name of an array tag type
+pub(crate) fn array_name(tag_type: TagType) -> Option<&'static str> {
+    match tag_type {
+        TagType::ByteArray => Some(BYTE_ARRAY),
+        TagType::IntArray => Some(INT_ARRAY),
+        TagType::LongArray => Some(LONG_ARRAY),
+        _ => None,
+    }
+}
+
+/// Hands an array tag to the `NbtTag` visitor as a map with a single entry
+/// named after the array type, whose value is the sequence of elements
+pub(crate) struct ArrayEntry<D> {
+    name: Option<&'static str>,
+    value: Option<D>,
+}
+
+impl<D> ArrayEntry<D> {
+    pub(crate) fn new(name: &'static str, value: D) -> Self {
+        Self {
+            name: Some(name),
+            value: Some(value),
+        }
+    }
+}
+
+impl<'de, D: Deserializer<'de, Error = Error>> de::MapAccess<'de> for ArrayEntry<D> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        self.name
+            .take()
+            .map(|name| seed.deserialize(de::value::BorrowedStrDeserializer::new(name)))
+            .transpose()
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("next_value called twice"))?;
+        seed.deserialize(value)
+    }
+}
+
+/// The type of the elements of an array tag
+pub(crate) fn elem_type(array: TagType) -> TagType {
+    match array {
+        TagType::ByteArray => TagType::Byte,
+        TagType::IntArray => TagType::Int,
+        _ => TagType::Long,
+    }
+}
+
+/// Serializes an element of an array tag into an integer that fits the element type.
+///
+/// Unsigned values keep their bit pattern like elsewhere, so the range of the
+/// unsigned type of the same width is accepted as well.
+pub(crate) struct ElementSerializer {
+    pub(crate) array: TagType,
+}
+
+impl ElementSerializer {
+    fn int(self, v: i64) -> Result<i64> {
+        let fits = match self.array {
+            TagType::ByteArray => (i8::MIN as i64..=u8::MAX as i64).contains(&v),
+            TagType::IntArray => (i32::MIN as i64..=u32::MAX as i64).contains(&v),
+            _ => true,
+        };
+        match fits {
+            true => Ok(v),
+            false => Err(Error::Message(format!(
+                "{v} is out of range for an element of a {:?}",
+                self.array
+            ))),
+        }
+    }
+
+    fn mismatch(self, actual: TagType) -> Error {
+        Error::TypeMismatch {
+            expected: elem_type(self.array),
+            actual,
+        }
+    }
+}
+
+impl ser::Serializer for ElementSerializer {
+    type Ok = i64;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<i64, Error>;
+    type SerializeTuple = Impossible<i64, Error>;
+    type SerializeTupleStruct = Impossible<i64, Error>;
+    type SerializeTupleVariant = Impossible<i64, Error>;
+    type SerializeMap = Impossible<i64, Error>;
+    type SerializeStruct = Impossible<i64, Error>;
+    type SerializeStructVariant = Impossible<i64, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<i64> {
+        Ok(v as i64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<i64> {
+        self.int(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<i64> {
+        self.int(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<i64> {
+        self.int(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<i64> {
+        self.int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<i64> {
+        self.int(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<i64> {
+        self.int(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<i64> {
+        self.int(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<i64> {
+        match self.array {
+            TagType::LongArray => Ok(v as i64),
+            _ => self.int(i64::try_from(v).unwrap_or(i64::MAX)),
+        }
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<i64> {
+        Err(self.mismatch(TagType::Float))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<i64> {
+        Err(self.mismatch(TagType::Double))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<i64> {
+        Err(self.mismatch(TagType::String))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<i64> {
+        Err(self.mismatch(TagType::String))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<i64> {
+        Err(self.mismatch(TagType::ByteArray))
+    }
+
+    fn serialize_none(self) -> Result<i64> {
+        Err(self.mismatch(TagType::End))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<i64> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<i64> {
+        Err(self.mismatch(TagType::End))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<i64> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<i64> {
+        Err(self.mismatch(TagType::String))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<i64> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<i64> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(self.mismatch(TagType::List))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(self.mismatch(TagType::List))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(self.mismatch(TagType::List))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(self.mismatch(TagType::Compound))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(self.mismatch(TagType::Compound))
+    }
+}
+
+/// Unwraps the newtype struct the helpers deserialize
+struct Unwrap<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for Unwrap<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<T, D::Error> {
+        T::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> std::result::Result<T, A::Error> {
+        T::deserialize(de::value::SeqAccessDeserializer::new(seq))
+    }
+}
+
+macro_rules! helper {
+    ($(#[$doc:meta])* $module:ident: $name:ident) => {
+        $(#[$doc])*
+        pub mod $module {
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                T: Serialize + ?Sized,
+                S: Serializer,
+            {
+                serializer.serialize_newtype_struct(super::$name, value)
+            }
+
+            pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+            where
+                T: Deserialize<'de>,
+                D: Deserializer<'de>,
+            {
+                deserializer
+                    .deserialize_newtype_struct(super::$name, super::Unwrap(std::marker::PhantomData))
+            }
+        }
+    };
+}
+
+helper! {
+    /// Stores a sequence of `u8` or `i8` as a ByteArray tag, and requires a ByteArray when reading
+    ///
+    /// ```
+    /// #[derive(serde::Serialize, serde::Deserialize)]
+    /// struct Chunk {
+    ///     #[serde(with = "nbt_rust::byte_array")]
+    ///     biomes: Vec<u8>,
+    /// }
+    /// ```
+    byte_array: BYTE_ARRAY
+}
+
+helper! {
+    /// Stores a sequence of integers as an IntArray tag, and requires an IntArray when reading
+    ///
+    /// ```
+    /// #[derive(serde::Serialize, serde::Deserialize)]
+    /// struct Entity {
+    ///     #[serde(rename = "UUID", with = "nbt_rust::int_array")]
+    ///     uuid: [i32; 4],
+    /// }
+    ///
+    /// let bytes = nbt_rust::to_bytes("", &Entity { uuid: [1, 2, 3, 4] }).unwrap();
+    /// let tag = nbt_rust::de::from_bytes(&bytes).unwrap().1;
+    /// assert_eq!(tag["UUID"].as_int_array(), Some(&[1, 2, 3, 4][..]));
+    /// ```
+    int_array: INT_ARRAY
+}
+
+helper! {
+    /// Stores a sequence of integers as a LongArray tag, and requires a LongArray when reading
+    long_array: LONG_ARRAY
+}
+
+helper! {
+    /// Stores a sequence as a List tag even if it serializes as bytes, such as `serde_bytes`
+    /// buffers, and requires a List when reading
+    list: LIST
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        de::from_bytes as read_tag, error::Error, from_bytes, from_value, nbt_tag::TagType,
+        to_bytes, to_value, NbtTag,
+    };
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Chunk {
+        #[serde(with = "crate::byte_array")]
+        biomes: Vec<u8>,
+        #[serde(with = "crate::byte_array")]
+        heights: Vec<i8>,
+        #[serde(with = "crate::int_array")]
+        uuid: [i32; 4],
+        #[serde(with = "crate::int_array")]
+        colors: Vec<u32>,
+        #[serde(with = "crate::long_array")]
+        states: Vec<i64>,
+        #[serde(default, with = "crate::long_array")]
+        light: Option<Vec<i64>>,
+        #[serde(with = "crate::list")]
+        raw: Vec<u8>,
+    }
+
+    fn chunk() -> Chunk {
+        Chunk {
+            biomes: vec![0, 255],
+            heights: vec![-128, 127],
+            uuid: [1, -2, 3, -4],
+            colors: vec![u32::MAX],
+            states: vec![i64::MIN, 0],
+            light: None,
+            raw: vec![7],
+        }
+    }
+
+    fn check_types(tag: &NbtTag) {
+        assert!(tag["biomes"] == NbtTag::ByteArray(vec![0, 255]));
+        assert!(tag["heights"] == NbtTag::ByteArray(vec![128, 127]));
+        assert!(tag["uuid"] == NbtTag::IntArray(vec![1, -2, 3, -4]));
+        // Unsigned values keep their bit pattern
+        assert!(tag["colors"] == NbtTag::IntArray(vec![-1]));
+        assert!(tag["states"] == NbtTag::LongArray(vec![i64::MIN, 0]));
+        assert!(tag.get("light").is_none());
+        assert_eq!(tag["raw"].as_list().unwrap().elem_type(), TagType::Byte);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let bytes = to_bytes("", &chunk()).unwrap();
+        check_types(&read_tag(&bytes).unwrap().1);
+        assert_eq!(from_bytes::<Chunk>(&bytes).unwrap(), chunk());
+
+        let lit = Chunk {
+            light: Some(vec![5]),
+            ..chunk()
+        };
+        let bytes = to_bytes("", &lit).unwrap();
+        assert_eq!(from_bytes::<Chunk>(&bytes).unwrap(), lit);
+    }
+
+    #[test]
+    fn round_trips_through_values() {
+        let tag = to_value(&chunk()).unwrap();
+        check_types(&tag);
+        assert_eq!(from_value::<Chunk>(&tag).unwrap(), chunk());
+    }
+
+    #[test]
+    fn elements_must_fit_the_array() {
+        #[derive(Serialize)]
+        struct Bytes {
+            #[serde(with = "crate::byte_array")]
+            v: Vec<i32>,
+        }
+
+        #[derive(Serialize)]
+        struct Floats {
+            #[serde(with = "crate::int_array")]
+            v: Vec<f32>,
+        }
+
+        let bytes = Bytes { v: vec![1, 300] };
+        for error in [
+            to_bytes("", &bytes).unwrap_err(),
+            to_value(&bytes).err().unwrap(),
+        ] {
+            assert!(error.to_string().contains("300 is out of range"), "{error}");
+            assert_eq!(error.path(), Some("v[1]"));
+        }
+
+        let floats = Floats { v: vec![1.0] };
+        for error in [
+            to_bytes("", &floats).unwrap_err(),
+            to_value(&floats).err().unwrap(),
+        ] {
+            assert!(matches!(
+                error.inner(),
+                Error::TypeMismatch {
+                    expected: TagType::Int,
+                    actual: TagType::Float
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn reading_requires_the_array_type() {
+        let mut tag = to_value(&chunk()).unwrap();
+        let list = NbtTag::from_snbt("[1, 2, 3, 4]").unwrap();
+        tag.as_compound_mut().unwrap().insert("uuid".into(), list);
+
+        let error = from_value::<Chunk>(&tag).err().unwrap();
+        assert!(matches!(
+            error.inner(),
+            Error::TypeMismatch {
+                expected: TagType::IntArray,
+                actual: TagType::List
+            }
+        ));
+        assert_eq!(error.path(), Some("uuid"));
+
+        let bytes = to_bytes("", &tag).unwrap();
+        let error = from_bytes::<Chunk>(&bytes).unwrap_err();
+        assert!(matches!(error.inner(), Error::TypeMismatch { .. }));
+        assert_eq!(error.path(), Some("uuid"));
+    }
+}
@@ -0,0 +1,883 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use crate::{
+    error::{Error, Result},
+    nbt_tag::{NbtCompound, NbtList, TagType},
+    NbtTag,
+};
+
+/// An error produced while parsing SNBT, pointing at the offending input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnbtError {
+    /// What went wrong
+    pub message: String,
+    /// 1-based line of the start of the span
+    pub line: usize,
+    /// 1-based column (in characters) of the start of the span
+    pub column: usize,
+    /// Byte range of the offending input
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for SnbtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for SnbtError {}
+
+impl NbtTag {
+    /// Parses a tag from its stringified form (SNBT),
+    /// as used in commands like `/give` and printed by `/data get`
+    ///
+    /// ```
+    /// use nbt_rust::NbtTag;
+    ///
+    /// let tag = NbtTag::from_snbt(r#"{Enchantments:[{id:"sharpness",lvl:5s}]}"#).unwrap();
+    /// ```
+    pub fn from_snbt(s: &str) -> Result<NbtTag> {
+        let mut parser = Parser { input: s, pos: 0 };
+
+        parser.skip_whitespace();
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+
+        if parser.pos < s.len() {
+            let start = parser.pos;
+            return Err(parser.error(start..s.len(), "Trailing data after the value"));
+        }
+
+        Ok(value)
+    }
+}
+
+impl FromStr for NbtTag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        NbtTag::from_snbt(s)
+    }
+}
+
+/// Characters that may appear in unquoted strings and keys
+#[inline]
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// SNBT parser, also used for the compounds in NBT paths
+pub(crate) struct Parser<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn error(&self, span: Range<usize>, message: impl Into<String>) -> Error {
+        let before = &self.input[..span.start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+
+        Error::Snbt(SnbtError {
+            message: message.into(),
+            line,
+            column,
+            span,
+        })
+    }
+
+    /// Error pointing at the next character, or at the end of the input
+    pub(crate) fn error_here(&self, message: impl Into<String>) -> Error {
+        let end = self.peek().map_or(self.pos, |c| self.pos + c.len_utf8());
+        self.error(self.pos..end, message)
+    }
+
+    #[inline]
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    #[inline]
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    #[inline]
+    pub(crate) fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    pub(crate) fn expect(&mut self, expected: char) -> Result<()> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.error_here(format!("Expected '{expected}', found '{c}'"))),
+            None => Err(self.error_here(format!("Expected '{expected}', found end of input"))),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<NbtTag> {
+        match self.peek() {
+            Some('{') => self.parse_compound(),
+            Some('[') => self.parse_list_or_array(),
+            Some('"' | '\'') => Ok(NbtTag::String(self.parse_quoted()?)),
+            Some(c) if is_unquoted_char(c) => {
+                let start = self.pos;
+                let token = self.parse_unquoted();
+                self.classify(token, start)
+            }
+            Some(c) => Err(self.error_here(format!("Unexpected character '{c}'"))),
+            None => Err(self.error_here("Expected a value, found end of input")),
+        }
+    }
+
+    pub(crate) fn parse_compound(&mut self) -> Result<NbtTag> {
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        let mut tags = NbtCompound::new();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(NbtTag::Compound(tags));
+        }
+
+        loop {
+            let key = self.parse_key()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            tags.insert(key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    self.skip_whitespace();
+                }
+                Some('}') => {
+                    self.bump();
+                    return Ok(NbtTag::Compound(tags));
+                }
+                _ => return Err(self.error_here("Expected ',' or '}' in compound")),
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String> {
+        match self.peek() {
+            Some('"' | '\'') => self.parse_quoted(),
+            Some(c) if is_unquoted_char(c) => Ok(self.parse_unquoted().to_string()),
+            _ => Err(self.error_here("Expected a key")),
+        }
+    }
+
+    fn parse_list_or_array(&mut self) -> Result<NbtTag> {
+        self.expect('[')?;
+
+        // Typed arrays start with the element type directly after the bracket, e.g. `[I;`
+        if self.peek_nth(1) == Some(';') {
+            let kind = match self.peek() {
+                Some('B') => TagType::Byte,
+                Some('I') => TagType::Int,
+                Some('L') => TagType::Long,
+                Some(c) => {
+                    return Err(self.error_here(format!("Invalid array type '{c}'")));
+                }
+                None => unreachable!(),
+            };
+            self.bump();
+            self.bump();
+            return self.parse_array(kind);
+        }
+
+        self.skip_whitespace();
+        let mut tags: Vec<NbtTag> = Vec::new();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(NbtTag::List(NbtList::new_empty()));
+        }
+
+        loop {
+            let value_start = self.pos;
+            let value = self.parse_value()?;
+            if let Some(first) = tags.first() {
+                if first.tag_type() != value.tag_type() {
+                    return Err(self.error(
+                        value_start..self.pos,
+                        format!(
+                            "Can't insert {:?} into a list of {:?}",
+                            value.tag_type(),
+                            first.tag_type()
+                        ),
+                    ));
+                }
+            }
+            tags.push(value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    self.skip_whitespace();
+                }
+                Some(']') => {
+                    self.bump();
+                    return Ok(NbtTag::List(NbtList::from_tags_unchecked(tags)));
+                }
+                _ => return Err(self.error_here("Expected ',' or ']' in list")),
+            }
+        }
+    }
+
+    /// Parses the elements of a typed array after its `[X;` prefix
+    fn parse_array(&mut self, kind: TagType) -> Result<NbtTag> {
+        let mut bytes = Vec::new();
+        let mut ints = Vec::new();
+        let mut longs = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() != Some(']') {
+            loop {
+                let value_start = self.pos;
+                match (kind, self.parse_value()?) {
+                    (TagType::Byte, NbtTag::Byte(v)) => bytes.push(v as u8),
+                    (TagType::Int, NbtTag::Int(v)) => ints.push(v),
+                    (TagType::Long, NbtTag::Long(v)) => longs.push(v),
+                    (_, value) => {
+                        return Err(self.error(
+                            value_start..self.pos,
+                            format!(
+                                "Can't insert {:?} into an array of {:?}",
+                                value.tag_type(),
+                                kind
+                            ),
+                        ));
+                    }
+                }
+
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(',') => {
+                        self.bump();
+                        self.skip_whitespace();
+                    }
+                    Some(']') => break,
+                    _ => return Err(self.error_here("Expected ',' or ']' in array")),
+                }
+            }
+        }
+
+        self.expect(']')?;
+
+        Ok(match kind {
+            TagType::Byte => NbtTag::ByteArray(bytes),
+            TagType::Int => NbtTag::IntArray(ints),
+            _ => NbtTag::LongArray(longs),
+        })
+    }
+
+    pub(crate) fn parse_quoted(&mut self) -> Result<String> {
+        let start = self.pos;
+        let Some(quote) = self.bump() else {
+            return Err(self.error_here("Expected a quoted string"));
+        };
+
+        let mut string = String::new();
+        loop {
+            let escape_start = self.pos;
+            match self.bump() {
+                Some(c) if c == quote => return Ok(string),
+                Some('\\') => {
+                    let c = match self.bump() {
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        Some('\'') => '\'',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => self.parse_unicode_escape(escape_start)?,
+                        Some(c) => {
+                            return Err(self.error(
+                                escape_start..self.pos,
+                                format!("Invalid escape sequence '\\{c}'"),
+                            ));
+                        }
+                        None => break,
+                    };
+                    string.push(c);
+                }
+                Some(c) => string.push(c),
+                None => break,
+            }
+        }
+
+        Err(self.error(start..self.pos, "Unterminated quoted string"))
+    }
+
+    /// Parses the four hex digits of a `\uXXXX` escape
+    fn parse_unicode_escape(&mut self, escape_start: usize) -> Result<char> {
+        let digits = self.input[self.pos..].get(..4).unwrap_or("");
+        let code = (digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| u32::from_str_radix(digits, 16).ok())
+            .flatten()
+            .and_then(char::from_u32);
+
+        match code {
+            Some(c) => {
+                self.pos += 4;
+                Ok(c)
+            }
+            None => Err(self.error(
+                escape_start..(self.pos + digits.len()),
+                "Invalid unicode escape sequence",
+            )),
+        }
+    }
+
+    fn parse_unquoted(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(is_unquoted_char) {
+            self.bump();
+        }
+        &self.input[start..self.pos]
+    }
+
+    /// Turns an unquoted token into a number, a boolean or a string
+    fn classify(&self, token: &str, start: usize) -> Result<NbtTag> {
+        let span = start..start + token.len();
+        let out_of_range = || self.error(span.clone(), format!("Number out of range: {token}"));
+
+        if token.eq_ignore_ascii_case("true") {
+            return Ok(NbtTag::Byte(1));
+        }
+        if token.eq_ignore_ascii_case("false") {
+            return Ok(NbtTag::Byte(0));
+        }
+
+        let (body, suffix) = match token.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&token[..i], Some(c.to_ascii_lowercase())),
+            _ => (token, None),
+        };
+
+        match suffix {
+            Some('b') if is_integer(body) => body
+                .parse::<i8>()
+                .map(NbtTag::Byte)
+                .map_err(|_| out_of_range()),
+            Some('s') if is_integer(body) => body
+                .parse::<i16>()
+                .map(NbtTag::Short)
+                .map_err(|_| out_of_range()),
+            Some('l') if is_integer(body) => body
+                .parse::<i64>()
+                .map(NbtTag::Long)
+                .map_err(|_| out_of_range()),
+            Some('f') if is_float(body, false) => body
+                .parse::<f32>()
+                .map(NbtTag::Float)
+                .map_err(|_| out_of_range()),
+            Some('d') if is_float(body, false) => body
+                .parse::<f64>()
+                .map(NbtTag::Double)
+                .map_err(|_| out_of_range()),
+            _ if is_integer(token) => token
+                .parse::<i32>()
+                .map(NbtTag::Int)
+                .map_err(|_| out_of_range()),
+            _ if is_float(token, true) => token
+                .parse::<f64>()
+                .map(NbtTag::Double)
+                .map_err(|_| out_of_range()),
+            _ => Ok(NbtTag::String(token.to_string())),
+        }
+    }
+}
+
+fn strip_sign(s: &str) -> &str {
+    s.strip_prefix(['+', '-']).unwrap_or(s)
+}
+
+/// Matches `[-+]?(0|[1-9][0-9]*)`
+fn is_integer(s: &str) -> bool {
+    let digits = strip_sign(s);
+    match digits.as_bytes() {
+        [] => false,
+        [b'0'] => true,
+        [b'0', ..] => false,
+        bytes => bytes.iter().all(u8::is_ascii_digit),
+    }
+}
+
+/// Matches `[-+]?([0-9]+[.]?|[0-9]*[.][0-9]+)(e[-+]?[0-9]+)?`,
+/// requiring the dot when `needs_dot` is set
+fn is_float(s: &str, needs_dot: bool) -> bool {
+    let s = strip_sign(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let (int_part, frac_part) = match mantissa.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (mantissa, None),
+    };
+
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let mantissa_ok = all_digits(int_part)
+        && frac_part.is_none_or(all_digits)
+        && (!int_part.is_empty() || frac_part.is_some_and(|f| !f.is_empty()));
+    let exponent_ok = exponent.is_none_or(|e| {
+        let e = strip_sign(e);
+        !e.is_empty() && all_digits(e)
+    });
+
+    mantissa_ok && exponent_ok && (!needs_dot || frac_part.is_some())
+}
+
+/// Writes tags as SNBT that Minecraft (and `NbtTag::from_snbt`) can read back
+///
+/// Keys are only quoted when they contain characters outside `[A-Za-z0-9._+-]`,
+/// and floating point numbers are written with the shortest digits
+/// that round-trip to the exact same value.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SnbtWriter {
+    /// Indentation per nesting level, or `None` for the compact single-line form
+    indent: Option<String>,
+}
+
+impl SnbtWriter {
+    /// Writes everything on a single line without spaces,
+    /// identical to the output of `/data get`
+    pub fn compact() -> Self {
+        Self { indent: None }
+    }
+
+    /// Writes one compound entry or list element per line, indented by four spaces
+    pub fn pretty() -> Self {
+        Self::with_indent("    ")
+    }
+
+    /// Like `pretty`, but indents with the given string
+    pub fn with_indent(indent: impl Into<String>) -> Self {
+        Self {
+            indent: Some(indent.into()),
+        }
+    }
+
+    /// Writes the tag to the given formatter or string
+    pub fn write<W: fmt::Write>(&self, w: &mut W, tag: &NbtTag) -> fmt::Result {
+        self.write_tag(w, tag, 0)
+    }
+
+    /// Writes the tag into a new string
+    pub fn to_string(&self, tag: &NbtTag) -> String {
+        let mut s = String::new();
+        self.write(&mut s, tag)
+            .expect("writing to a String cannot fail");
+        s
+    }
+
+    fn write_tag<W: fmt::Write>(&self, w: &mut W, tag: &NbtTag, depth: usize) -> fmt::Result {
+        match tag {
+            NbtTag::End => write!(w, "END"),
+            NbtTag::Byte(v) => write!(w, "{v}b"),
+            NbtTag::Short(v) => write!(w, "{v}s"),
+            NbtTag::Int(v) => write!(w, "{v}"),
+            NbtTag::Long(v) => write!(w, "{v}L"),
+            NbtTag::Float(v) => {
+                write_float(w, format!("{v:e}"))?;
+                write!(w, "f")
+            }
+            NbtTag::Double(v) => {
+                write_float(w, format!("{v:e}"))?;
+                write!(w, "d")
+            }
+            NbtTag::String(v) => write_quoted(w, v),
+            NbtTag::ByteArray(v) => {
+                self.write_array(w, "B", v.iter().map(|b| format!("{}B", *b as i8)))
+            }
+            NbtTag::IntArray(v) => self.write_array(w, "I", v.iter().map(|i| i.to_string())),
+            NbtTag::LongArray(v) => self.write_array(w, "L", v.iter().map(|l| format!("{l}L"))),
+            NbtTag::List(v) => self.write_list(w, v, depth),
+            NbtTag::Compound(v) => self.write_compound(w, v, depth),
+        }
+    }
+
+    fn write_array<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        prefix: &str,
+        values: impl Iterator<Item = String>,
+    ) -> fmt::Result {
+        let separator = if self.indent.is_some() { ", " } else { "," };
+
+        write!(w, "[{prefix};")?;
+        for (i, v) in values.enumerate() {
+            match i {
+                0 if self.indent.is_some() => write!(w, " ")?,
+                0 => {}
+                _ => write!(w, "{separator}")?,
+            }
+            write!(w, "{v}")?;
+        }
+        write!(w, "]")
+    }
+
+    fn write_list<W: fmt::Write>(&self, w: &mut W, v: &[NbtTag], depth: usize) -> fmt::Result {
+        let nested = v
+            .iter()
+            .any(|t| matches!(t, NbtTag::List(_) | NbtTag::Compound(_)));
+
+        match &self.indent {
+            // Lists of plain values stay on one line even when pretty printing
+            Some(_) if !nested => {
+                write!(w, "[")?;
+                for (i, tag) in v.iter().enumerate() {
+                    if i != 0 {
+                        write!(w, ", ")?;
+                    }
+                    self.write_tag(w, tag, depth)?;
+                }
+                write!(w, "]")
+            }
+            Some(indent) if !v.is_empty() => {
+                writeln!(w, "[")?;
+                for (i, tag) in v.iter().enumerate() {
+                    if i != 0 {
+                        writeln!(w, ",")?;
+                    }
+                    write!(w, "{}", indent.repeat(depth + 1))?;
+                    self.write_tag(w, tag, depth + 1)?;
+                }
+                write!(w, "\n{}]", indent.repeat(depth))
+            }
+            _ => {
+                write!(w, "[")?;
+                for (i, tag) in v.iter().enumerate() {
+                    if i != 0 {
+                        write!(w, ",")?;
+                    }
+                    self.write_tag(w, tag, depth)?;
+                }
+                write!(w, "]")
+            }
+        }
+    }
+
+    fn write_compound<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        v: &NbtCompound,
+        depth: usize,
+    ) -> fmt::Result {
+        match &self.indent {
+            Some(indent) if !v.is_empty() => {
+                writeln!(w, "{{")?;
+                for (i, (key, tag)) in v.iter().enumerate() {
+                    if i != 0 {
+                        writeln!(w, ",")?;
+                    }
+                    write!(w, "{}", indent.repeat(depth + 1))?;
+                    write_key(w, key)?;
+                    write!(w, ": ")?;
+                    self.write_tag(w, tag, depth + 1)?;
+                }
+                write!(w, "\n{}}}", indent.repeat(depth))
+            }
+            _ => {
+                write!(w, "{{")?;
+                for (i, (key, tag)) in v.iter().enumerate() {
+                    if i != 0 {
+                        write!(w, ",")?;
+                    }
+                    write_key(w, key)?;
+                    write!(w, ":")?;
+                    self.write_tag(w, tag, depth)?;
+                }
+                write!(w, "}}")
+            }
+        }
+    }
+}
+
+/// Writes a compound key, quoting it only if it cannot be read back unquoted
+fn write_key<W: fmt::Write>(w: &mut W, key: &str) -> fmt::Result {
+    if !key.is_empty() && key.chars().all(is_unquoted_char) {
+        write!(w, "{key}")
+    } else {
+        write_quoted(w, key)
+    }
+}
+
+/// Quotes a string the way Minecraft does: with double quotes,
+/// unless the string contains a double quote but no single quote
+fn write_quoted<W: fmt::Write>(w: &mut W, s: &str) -> fmt::Result {
+    let quote = match s.find(['"', '\'']) {
+        Some(i) if s[i..].starts_with('"') => '\'',
+        _ => '"',
+    };
+
+    write!(w, "{quote}")?;
+    for c in s.chars() {
+        if c == '\\' || c == quote {
+            write!(w, "\\")?;
+        }
+        write!(w, "{c}")?;
+    }
+    write!(w, "{quote}")
+}
+
+/// Writes a float given in Rust's shortest round-trip exponent form (`1.5e7`)
+/// the way Java's `toString` lays it out (`1.5E7`, `0.001`, `20.0`)
+fn write_float<W: fmt::Write>(w: &mut W, repr: String) -> fmt::Result {
+    let Some((mantissa, exponent)) = repr.split_once('e') else {
+        // NaN and infinities, which Java spells out like this too
+        return match repr.trim_start_matches('-') {
+            "inf" if repr.starts_with('-') => write!(w, "-Infinity"),
+            "inf" => write!(w, "Infinity"),
+            _ => write!(w, "NaN"),
+        };
+    };
+
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => ("-", m),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().map_err(|_| fmt::Error)?;
+
+    if (-3..7).contains(&exponent) {
+        if exponent < 0 {
+            let zeros = "0".repeat((-exponent - 1) as usize);
+            return write!(w, "{sign}0.{zeros}{digits}");
+        }
+
+        let point = exponent as usize + 1;
+        if digits.len() > point {
+            write!(w, "{sign}{}.{}", &digits[..point], &digits[point..])
+        } else {
+            let zeros = "0".repeat(point - digits.len());
+            write!(w, "{sign}{digits}{zeros}.0")
+        }
+    } else {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        write!(w, "{sign}{}.{fraction}E{exponent}", &digits[..1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(snbt: &str) -> NbtTag {
+        NbtTag::from_snbt(snbt).unwrap()
+    }
+
+    /// Parses invalid SNBT, returning the error's message, line and column
+    fn parse_err(snbt: &str) -> (String, usize, usize) {
+        match NbtTag::from_snbt(snbt) {
+            Err(Error::Snbt(e)) => (e.message, e.line, e.column),
+            Err(e) => panic!("{snbt:?} failed with {e}"),
+            Ok(_) => panic!("{snbt:?} was parsed"),
+        }
+    }
+
+    #[test]
+    fn parses_every_tag_type() {
+        let tag = parse(
+            r#"{b: 1b, s: -2S, i: +3, l: 4L, f: 1.5f, d: 2.5, e: 1e3d, t: true, F: FALSE,
+                str: "minecraft:stone", q: 'say "hi"', esc: "a\né\\",
+                bytes: [B; 1b, -1B], ints: [I;], longs: [L; 1L, -9223372036854775808L],
+                list: [[], [1, 2]], c: {"key with spaces": {}}}"#,
+        );
+        assert!(tag["b"] == NbtTag::Byte(1));
+        assert!(tag["s"] == NbtTag::Short(-2));
+        assert!(tag["i"] == NbtTag::Int(3));
+        assert!(tag["l"] == NbtTag::Long(4));
+        assert!(tag["f"] == NbtTag::Float(1.5));
+        assert!(tag["d"] == NbtTag::Double(2.5));
+        assert!(tag["e"] == NbtTag::Double(1000.0));
+        assert!(tag["t"] == NbtTag::Byte(1) && tag["F"] == NbtTag::Byte(0));
+        assert_eq!(tag["str"].as_str(), Some("minecraft:stone"));
+        assert_eq!(tag["q"].as_str(), Some("say \"hi\""));
+        assert_eq!(tag["esc"].as_str(), Some("a\n\u{e9}\\"));
+        assert!(tag["bytes"] == NbtTag::ByteArray(vec![1, 255]));
+        assert!(tag["ints"] == NbtTag::IntArray(vec![]));
+        assert!(tag["longs"] == NbtTag::LongArray(vec![1, i64::MIN]));
+        assert_eq!(tag["list"][0].as_list().unwrap().elem_type(), TagType::End);
+        assert_eq!(tag["list"][1].as_list().unwrap().elem_type(), TagType::Int);
+        assert!(tag["c"]["key with spaces"] == NbtTag::Compound(NbtCompound::new()));
+    }
+
+    #[test]
+    fn unquoted_tokens_that_are_not_numbers_are_strings() {
+        // Leading zeros, a missing dot without a suffix and unknown suffixes
+        for token in ["012", "1e3", "3x", "1.2.3", "-", "+"] {
+            assert_eq!(parse(token).as_str(), Some(token));
+        }
+        assert!(parse(".5") == NbtTag::Double(0.5));
+        assert!(parse("7.") == NbtTag::Double(7.0));
+        assert!(parse("1e-5f") == NbtTag::Float(1e-5));
+    }
+
+    #[test]
+    fn from_str_parses_snbt() {
+        let tag: NbtTag = "[1s, 2s]".parse().unwrap();
+        assert_eq!(tag.as_list().unwrap().elem_type(), TagType::Short);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_input() {
+        let (message, line, column) = parse_err("{\n  a: 1,\n  b: [1, \"two\"]\n}");
+        assert_eq!(message, "Can't insert String into a list of Int");
+        assert_eq!((line, column), (3, 10));
+
+        let (message, line, column) = parse_err("{a: 128b}");
+        assert_eq!(message, "Number out of range: 128b");
+        assert_eq!((line, column), (1, 5));
+
+        let (message, _, column) = parse_err("[I; 1, 2L]");
+        assert_eq!(message, "Can't insert Long into an array of Int");
+        assert_eq!(column, 8);
+
+        let (message, _, column) = parse_err("{a: 1} x");
+        assert_eq!(message, "Trailing data after the value");
+        assert_eq!(column, 8);
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let (_, line, column) = parse_err("{\"\u{e9}\u{e9}\": 1, \"\u{1F600}\": [Q; 1]}");
+        assert_eq!((line, column), (1, 17));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for (snbt, expected) in [
+            ("", "Expected a value, found end of input"),
+            ("{a 1}", "Expected ':', found '1'"),
+            ("{a: 1 b: 2}", "Expected ',' or '}' in compound"),
+            ("[1 2]", "Expected ',' or ']' in list"),
+            ("[Q; 1]", "Invalid array type 'Q'"),
+            ("\"open", "Unterminated quoted string"),
+            (r#""\q""#, "Invalid escape sequence '\\q'"),
+            (r#""\u12""#, "Invalid unicode escape sequence"),
+            ("{: 1}", "Expected a key"),
+            ("@", "Unexpected character '@'"),
+        ] {
+            assert_eq!(parse_err(snbt).0, expected, "{snbt:?}");
+        }
+    }
+
+    fn compact(tag: &NbtTag) -> String {
+        SnbtWriter::compact().to_string(tag)
+    }
+
+    #[test]
+    fn writes_every_tag_type() {
+        for (tag, snbt) in [
+            (NbtTag::Byte(-1), "-1b"),
+            (NbtTag::Short(300), "300s"),
+            (NbtTag::Int(7), "7"),
+            (NbtTag::Long(i64::MIN), "-9223372036854775808L"),
+            (NbtTag::Float(20.0), "20.0f"),
+            (NbtTag::Double(-0.5), "-0.5d"),
+            (NbtTag::ByteArray(vec![1, 255]), "[B;1B,-1B]"),
+            (NbtTag::IntArray(vec![]), "[I;]"),
+            (NbtTag::LongArray(vec![2]), "[L;2L]"),
+            (NbtTag::List(NbtList::new(TagType::Compound)), "[]"),
+            (NbtTag::Compound(NbtCompound::new()), "{}"),
+        ] {
+            assert_eq!(compact(&tag), snbt);
+        }
+    }
+
+    #[test]
+    fn floats_are_laid_out_like_java() {
+        for (v, snbt) in [
+            (0.1, "0.1d"),
+            (1.5e7, "1.5E7d"),
+            (1234567.0, "1234567.0d"),
+            (0.001, "0.001d"),
+            (1e-4, "1.0E-4d"),
+            (f64::NAN, "NaNd"),
+            (f64::NEG_INFINITY, "-Infinityd"),
+        ] {
+            assert_eq!(compact(&NbtTag::Double(v)), snbt);
+        }
+        // The shortest digits of the f32 itself, not of its f64 widening
+        assert_eq!(compact(&NbtTag::Float(0.1)), "0.1f");
+    }
+
+    #[test]
+    fn quotes_only_when_needed() {
+        for (s, snbt) in [
+            ("plain", r#""plain""#),
+            ("say \"hi\"", r#"'say "hi"'"#),
+            ("it's", r#""it's""#),
+            ("both ' and \"", r#""both ' and \"""#),
+            ("back\\slash", r#""back\\slash""#),
+        ] {
+            assert_eq!(compact(&NbtTag::from(s)), snbt);
+        }
+
+        let mut compound = NbtCompound::new();
+        compound.insert("minecraft:id".into(), 1.into());
+        assert_eq!(
+            compact(&NbtTag::Compound(compound)),
+            r#"{"minecraft:id":1}"#
+        );
+        let mut compound = NbtCompound::new();
+        compound.insert("".into(), 1.into());
+        assert_eq!(compact(&NbtTag::Compound(compound)), r#"{"":1}"#);
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn pretty_printing_indents_nested_tags() {
+        let tag = parse("{id:\"stone\",Pos:[1.0d,2.0d],Items:[{Slot:0b},{}],Empty:{}}");
+        assert_eq!(
+            SnbtWriter::pretty().to_string(&tag),
+            "{\n    id: \"stone\",\n    Pos: [1.0d, 2.0d],\n    Items: [\n        {\n            \
+             Slot: 0b\n        },\n        {}\n    ],\n    Empty: {}\n}"
+        );
+        assert_eq!(
+            SnbtWriter::with_indent("\t").to_string(&parse("{a:[I;1,2]}")),
+            "{\n\ta: [I; 1, 2]\n}"
+        );
+        assert_eq!(
+            compact(&tag),
+            r#"{id:"stone",Pos:[1.0d,2.0d],Items:[{Slot:0b},{}],Empty:{}}"#
+        );
+    }
+
+    #[test]
+    fn written_snbt_parses_back() {
+        let tag = parse(
+            r#"{b: -128b, s: 32767s, l: 9223372036854775807L, f: 3.4028235e38f,
+                tiny: 4.9e-324d, neg: -0.0d, str: "tab\tquote\"'", "odd key": {"": [[B;], [B; 1b]]},
+                list: [{a: 1}, {b: [L; -1L]}], nested: [[1.5f], [2.5f]]}"#,
+        );
+        for writer in [SnbtWriter::compact(), SnbtWriter::pretty()] {
+            assert!(parse(&writer.to_string(&tag)) == tag);
+        }
+    }
+}
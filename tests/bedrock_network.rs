@@ -0,0 +1,91 @@
+//! Round trips of Bedrock network NBT payloads through the `BedrockNetwork` flavor
+
+use nbt_rust::{
+    de::from_bytes_flavored,
+    error::Error,
+    flavor::{BedrockNetwork, Java},
+    nbt_tag::TagType,
+    ser::Serializer,
+    NbtTag,
+};
+
+const SIGN: &[u8] = include_bytes!("fixtures/bedrock_network/sign_block_actor.bin");
+const ITEM: &[u8] = include_bytes!("fixtures/bedrock_network/item_user_data.bin");
+const EVERY_TAG_TYPE: &[u8] = include_bytes!("fixtures/bedrock_network/every_tag_type.bin");
+const LONG_STRING: &[u8] = include_bytes!("fixtures/bedrock_network/long_string.bin");
+
+fn decode(bytes: &[u8]) -> NbtTag {
+    let (name, tag) = from_bytes_flavored(bytes, BedrockNetwork).unwrap();
+    assert_eq!(name, "");
+    tag
+}
+
+fn encode(tag: &NbtTag) -> Vec<u8> {
+    let mut ser = Serializer::new(Vec::new()).with_flavor(BedrockNetwork);
+    ser.serialize_tag("", tag).unwrap();
+    ser.into_inner().unwrap()
+}
+
+/// Re-encodes the payload, which gives back the same bytes as long as
+/// compounds keep their order
+fn assert_round_trip(bytes: &[u8]) {
+    let tag = decode(bytes);
+    let encoded = encode(&tag);
+    if cfg!(feature = "preserve_order") {
+        assert!(encoded == bytes, "re-encoding changed the payload");
+    } else {
+        assert!(decode(&encoded) == tag);
+    }
+}
+
+#[test]
+fn sign_block_actor() {
+    let sign = decode(SIGN);
+    assert_eq!(sign["id"].as_str(), Some("Sign"));
+    assert_eq!(sign["FrontText"]["Text"].as_str(), Some("Hello\nWorld"));
+    assert_eq!(sign["FrontText"]["Color"].as_i32(), Some(-16777216));
+    assert_eq!(
+        (sign["x"].as_i32(), sign["y"].as_i32(), sign["z"].as_i32()),
+        (Some(12), Some(-60), Some(-345))
+    );
+    assert_round_trip(SIGN);
+}
+
+#[test]
+fn item_user_data() {
+    let item = decode(ITEM);
+    assert_eq!(item["Damage"].as_i32(), Some(17));
+    assert_eq!(item["display"]["Lore"][1].as_str(), Some("\u{1F5E1} sharp"));
+    assert_eq!(item["ench"][1]["id"].as_i16(), Some(17));
+    assert_round_trip(ITEM);
+}
+
+#[test]
+fn every_tag_type() {
+    let tag = decode(EVERY_TAG_TYPE);
+    assert_eq!(tag["int_min"].as_i32(), Some(i32::MIN));
+    assert_eq!(tag["long_min"].as_i64(), Some(i64::MIN));
+    assert_eq!(tag["double"].as_f64(), Some(-1.25e300));
+    assert_eq!(
+        tag["ints"].as_int_array(),
+        Some(&[0, -1, 300, i32::MIN][..])
+    );
+    assert_eq!(tag["longs"].as_long_array(), Some(&[i64::MAX, -5][..]));
+    let empty = tag["empty_compounds"].as_list().unwrap();
+    assert!(empty.is_empty() && empty.elem_type() == TagType::Compound);
+    assert_round_trip(EVERY_TAG_TYPE);
+}
+
+#[test]
+fn strings_longer_than_a_short() {
+    let book = decode(LONG_STRING);
+    let text = book["text"].as_str().unwrap();
+    assert_eq!(text.len(), 67200);
+    assert_round_trip(LONG_STRING);
+
+    // Java prefixes strings with an unsigned short, which cannot hold the length
+    let mut ser = Serializer::new(Vec::new()).with_flavor(Java);
+    let error = ser.serialize_tag("", &book).unwrap_err();
+    assert!(matches!(error.inner(), Error::StringTooLong(67200)));
+    assert_eq!(error.path(), Some("text"));
+}
@@ -0,0 +1,61 @@
+//! Array fields of `#[derive(Nbt)]` structs
+
+#![cfg(feature = "macros")]
+
+use nbt_rust::{FromNbt, Nbt, NbtTag, ToNbt};
+
+#[derive(Nbt, Debug, PartialEq)]
+struct Section {
+    #[nbt(rename = "Y")]
+    y: i8,
+    #[nbt(rename = "BlockLight", byte_array)]
+    block_light: Vec<u8>,
+    #[nbt(rename = "BlockStates", long_array)]
+    block_states: Option<Vec<i64>>,
+    #[nbt(rename = "UUID", int_array)]
+    owner: Option<[i32; 4]>,
+}
+
+#[test]
+fn optional_arrays_round_trip() {
+    let section = Section {
+        y: -4,
+        block_light: vec![0, 255],
+        block_states: Some(vec![i64::MIN, 3]),
+        owner: Some([1, 2, 3, 4]),
+    };
+    let tag = section.to_nbt();
+    assert!(tag["BlockLight"] == NbtTag::ByteArray(vec![0, 255]));
+    assert!(tag["BlockStates"] == NbtTag::LongArray(vec![i64::MIN, 3]));
+    assert!(tag["UUID"] == NbtTag::IntArray(vec![1, 2, 3, 4]));
+    assert_eq!(Section::from_nbt(&tag).unwrap(), section);
+}
+
+#[test]
+fn missing_optional_arrays_are_none() {
+    let section = Section {
+        y: 0,
+        block_light: vec![],
+        block_states: None,
+        owner: None,
+    };
+    let tag = section.to_nbt();
+    assert!(tag.get("BlockStates").is_none() && tag.get("UUID").is_none());
+    assert_eq!(Section::from_nbt(&tag).unwrap(), section);
+}
+
+#[test]
+fn array_fields_check_the_tag_type() {
+    let mut tag = Section {
+        y: 0,
+        block_light: vec![],
+        block_states: None,
+        owner: None,
+    }
+    .to_nbt();
+    tag.as_compound_mut()
+        .unwrap()
+        .insert("UUID".into(), NbtTag::LongArray(vec![1, 2]));
+    let error = Section::from_nbt(&tag).err().unwrap();
+    assert_eq!(error.path(), Some("UUID"));
+}
@@ -0,0 +1,45 @@
+//! Lists built by `nbt!` from interpolated expressions, whose types are only known at run time
+
+#![cfg(feature = "macros")]
+
+use nbt_rust::{nbt, nbt_tag::TagType};
+
+#[test]
+fn expressions_matching_the_list_type() {
+    let (x, y) = (1.5f64, -2.0f64);
+    let pos = nbt!([x, y, 3.0d]);
+    let list = pos.as_list().unwrap();
+    assert_eq!(list.elem_type(), TagType::Double);
+    assert_eq!(list.len(), 3);
+
+    let names = ["a", "b"];
+    let tags = nbt!([names[0], names[1]]);
+    assert_eq!(tags.as_list().unwrap().elem_type(), TagType::String);
+}
+
+#[test]
+fn empty_list() {
+    let tag = nbt!([]);
+    assert_eq!(tag.as_list().unwrap().elem_type(), TagType::End);
+}
+
+#[test]
+fn nested_lists_may_hold_different_types() {
+    let inner = "x";
+    let tag = nbt!([[1b, 2b], [inner]]);
+    assert_eq!(tag[1].as_list().unwrap().elem_type(), TagType::String);
+}
+
+#[test]
+#[should_panic(expected = "nbt! list element 2 is a String tag, but the list holds Int tags")]
+fn expression_of_another_type() {
+    let name = "Steve";
+    nbt!([1, 2, name]);
+}
+
+#[test]
+#[should_panic(expected = "nbt! list element 1 is a Byte tag, but the list holds Long tags")]
+fn first_expression_sets_the_list_type() {
+    let (a, b) = (1i64, 1i8);
+    nbt!([a, b]);
+}